@@ -15,6 +15,11 @@ let wasmModule: {
 } | null = null;
 let wasmInitialized = false;
 
+// Banner grid for the text under the sphere: 13 glyphs per line and two
+// lines of 7 rows, e.g. "O.ASCII Art" over "generator"
+const TEXT_COLUMNS = 80;
+const TEXT_ROWS = 15;
+
 const initWasm = async (): Promise<void> => {
   if (!wasmInitialized && typeof window !== "undefined") {
     try {
@@ -67,7 +72,7 @@ export const useSphereAnimation = (enabled: boolean = true, text: string = "Gene
       
       // Generate text frame when initialized or text changes
       if (initialized && wasmModule) {
-        const textAscii = wasmModule.generate_text_in_center(text, 40, 5);
+        const textAscii = wasmModule.generate_text_in_center(text, TEXT_COLUMNS, TEXT_ROWS);
         setTextFrame(textAscii);
       }
     };
@@ -4,10 +4,15 @@
  */
 
 import { ASCII_CHARS } from "../store/ascii-store";
+import { convertCanvasToAscii } from "./ascii-converter";
 
 // Type declarations for WASM module are in wasm-ascii.d.ts at project root
 // This module will exist after running 'npm run build:wasm'
 declare module "../../../wasm-ascii/pkg/wasm_ascii" {
+  /**
+   * @throws {string} for options it can't use: an unknown dither algorithm
+   * or edge method, an empty charset, or data not sized width * height * 4
+   */
   export function convert_to_ascii(
     data: Uint8Array,
     width: number,
@@ -32,6 +37,7 @@ declare module "../../../wasm-ascii/pkg/wasm_ascii" {
 
 // Type definition for WASM module
 type WasmModule = {
+  /** Throws a string error message for options it can't use */
   convert_to_ascii: (
     data: Uint8Array,
     width: number,
@@ -99,25 +105,52 @@ interface ConvertToAsciiOptions {
   contrast: number[];
 }
 
+// Run the WASM converter on the canvas pixels; if it rejects the options,
+// fall back to the TypeScript converter so the UI still gets output
+const convertWithFallback = (
+  wasm: WasmModule,
+  data: Uint8Array,
+  height: number,
+  options: ConvertToAsciiOptions
+): string => {
+  // Get character set string
+  const charsetString =
+    options.charset === "manual"
+      ? options.manualChar
+      : ASCII_CHARS[options.charset];
+
+  try {
+    return wasm.convert_to_ascii(
+      data,
+      options.width,
+      height,
+      options.invert,
+      charsetString,
+      options.manualChar,
+      options.ignoreWhite,
+      options.dithering,
+      options.ditherAlgorithm,
+      options.edgeMethod,
+      options.edgeThreshold[0],
+      options.dogThreshold[0],
+      options.brightness[0],
+      options.contrast[0]
+    );
+  } catch (error) {
+    console.warn("WASM conversion failed, falling back to TypeScript:", error);
+    return convertCanvasToAscii(options);
+  }
+};
+
 /**
  * Convert canvas to ASCII art using WASM
  * This is a drop-in replacement for the TypeScript version
  */
-export const convertCanvasToAsciiWasm = async ({
-  canvas,
-  width,
-  invert,
-  charset,
-  manualChar,
-  ignoreWhite,
-  dithering,
-  ditherAlgorithm,
-  edgeMethod,
-  edgeThreshold,
-  dogThreshold,
-  brightness,
-  contrast,
-}: ConvertToAsciiOptions): Promise<string> => {
+export const convertCanvasToAsciiWasm = async (
+  options: ConvertToAsciiOptions
+): Promise<string> => {
+  const { canvas, width } = options;
+
   // Ensure WASM is initialized
   if (!wasmInitialized) {
     await initWasm();
@@ -130,10 +163,6 @@ export const convertCanvasToAsciiWasm = async ({
   const imageData = ctx.getImageData(0, 0, width, height);
   const data = new Uint8Array(imageData.data);
 
-  // Get character set string
-  const charsetString =
-    charset === "manual" ? manualChar : ASCII_CHARS[charset];
-
   // Call WASM function
   if (!wasmModule) {
     throw new Error(
@@ -141,45 +170,18 @@ export const convertCanvasToAsciiWasm = async ({
     );
   }
 
-  const ascii = wasmModule.convert_to_ascii(
-    data,
-    width,
-    height,
-    invert,
-    charsetString,
-    manualChar,
-    ignoreWhite,
-    dithering,
-    ditherAlgorithm,
-    edgeMethod,
-    edgeThreshold[0],
-    dogThreshold[0],
-    brightness[0],
-    contrast[0]
-  );
-
-  return ascii;
+  return convertWithFallback(wasmModule, data, height, options);
 };
 
 /**
  * Synchronous version (requires WASM to be pre-initialized)
  * Use this if you've already called initWasm() at app startup
  */
-export const convertCanvasToAsciiWasmSync = ({
-  canvas,
-  width,
-  invert,
-  charset,
-  manualChar,
-  ignoreWhite,
-  dithering,
-  ditherAlgorithm,
-  edgeMethod,
-  edgeThreshold,
-  dogThreshold,
-  brightness,
-  contrast,
-}: ConvertToAsciiOptions): string => {
+export const convertCanvasToAsciiWasmSync = (
+  options: ConvertToAsciiOptions
+): string => {
+  const { canvas, width } = options;
+
   if (!wasmInitialized) {
     throw new Error(
       "WASM not initialized. Call initWasm() first or use convertCanvasToAsciiWasm()"
@@ -193,10 +195,6 @@ export const convertCanvasToAsciiWasmSync = ({
   const imageData = ctx.getImageData(0, 0, width, height);
   const data = new Uint8Array(imageData.data);
 
-  // Get character set string
-  const charsetString =
-    charset === "manual" ? manualChar : ASCII_CHARS[charset];
-
   // Call WASM function
   if (!wasmModule) {
     throw new Error(
@@ -204,24 +202,7 @@ export const convertCanvasToAsciiWasmSync = ({
     );
   }
 
-  const ascii = wasmModule.convert_to_ascii(
-    data,
-    width,
-    height,
-    invert,
-    charsetString,
-    manualChar,
-    ignoreWhite,
-    dithering,
-    ditherAlgorithm,
-    edgeMethod,
-    edgeThreshold[0],
-    dogThreshold[0],
-    brightness[0],
-    contrast[0]
-  );
-
-  return ascii;
+  return convertWithFallback(wasmModule, data, height, options);
 };
 
 // Re-export canvas creation functions (these stay in TypeScript)
@@ -0,0 +1,271 @@
+use wasm_bindgen::prelude::*;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::imageops::FilterType;
+use image::{AnimationDecoder, Delay, Frame, ImageDecoder, ImageFormat, RgbaImage};
+use std::io::Cursor;
+
+use crate::convert_options::ConvertOptions;
+use crate::color_output::ColorFormat;
+use crate::frame_size::check_frame_size;
+use crate::image_compression::{decode_oriented, DecodeLimits};
+use crate::rasterize::{render_ascii_to_rgb, RenderOptions};
+use crate::convert_with_options;
+
+// Most frames decoded from one animation; every frame is held in memory
+// until conversion, so a long animation costs as much as a large image
+const MAX_ANIMATION_FRAMES: usize = 1000;
+
+/// ASCII frames of an animation with their display durations
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct AsciiAnimation {
+    frames: Vec<String>,
+    delays: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl AsciiAnimation {
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// ASCII art of frame `index`, or `undefined` past the last frame
+    pub fn frame(&self, index: usize) -> Option<String> {
+        self.frames.get(index).cloned()
+    }
+
+    pub fn frames(&self) -> Vec<String> {
+        self.frames.clone()
+    }
+
+    /// Display duration of each frame in milliseconds
+    pub fn delays(&self) -> Vec<u32> {
+        self.delays.clone()
+    }
+
+    /// Render every frame with `render` and encode an endlessly looping GIF
+    ///
+    /// Frames must have been converted as plain text; per-cell colors from
+    /// `render` apply to every frame.
+    pub fn to_gif(&self, render: &RenderOptions) -> Result<Vec<u8>, String> {
+        let mut frames = Vec::with_capacity(self.frames.len());
+        for (text, &delay) in self.frames.iter().zip(&self.delays) {
+            let (rgb, width, height) = render_ascii_to_rgb(text, render)?;
+            let rgba: Vec<u8> = rgb.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect();
+            let buffer = RgbaImage::from_raw(width as u32, height as u32, rgba)
+                .ok_or_else(|| "Rendered frame has an unexpected size".to_string())?;
+            frames.push(Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(delay, 1)));
+        }
+
+        let mut output = Vec::new();
+        {
+            let mut encoder = GifEncoder::new_with_speed(&mut output, 10);
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+            encoder
+                .encode_frames(frames)
+                .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+        }
+        Ok(output)
+    }
+}
+
+/// Convert every frame of an animated GIF or APNG to ASCII art
+///
+/// Frames are composed onto the full canvas (honoring GIF disposal), then
+/// resized and converted with the same options. Still images of any
+/// supported format become a single frame with a delay of 0.
+///
+/// Decoding is bounded by the default `DecodeLimits`: each frame must fit
+/// them, all frames together must fit its allocation limit, and there may
+/// be at most 1000 frames.
+///
+/// # Arguments
+/// * `image_data` - Encoded GIF, APNG or still image bytes
+/// * `width` - Pixels per row fed to the converter; 0 keeps the source width
+/// * `height` - Pixel rows fed to the converter; 0 keeps the source height
+/// * `options` - Conversion options applied to every frame
+///
+/// # Returns
+/// The ASCII frames with their delays
+#[wasm_bindgen]
+pub fn convert_animation(
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    options: &ConvertOptions,
+) -> Result<AsciiAnimation, String> {
+    let decoded = decode_frames(image_data, &DecodeLimits::default())?;
+
+    let mut frames = Vec::with_capacity(decoded.len());
+    let mut delays = Vec::with_capacity(decoded.len());
+    for (index, (image, delay)) in decoded.into_iter().enumerate() {
+        let target_width = if width == 0 { image.width() } else { width };
+        let target_height = if height == 0 { image.height() } else { height };
+        check_frame_size("Frame size", "pixels", target_width as usize, target_height as usize)?;
+        let image = if (target_width, target_height) == image.dimensions() {
+            image
+        } else {
+            image::imageops::resize(&image, target_width, target_height, FilterType::Triangle)
+        };
+
+        let text = convert_with_options(image.as_raw(), target_width as usize, target_height as usize, options)
+            .map_err(|e| format!("Frame {}: {}", index, e))?;
+        frames.push(text);
+        delays.push(delay);
+    }
+
+    Ok(AsciiAnimation { frames, delays })
+}
+
+/// Convert an animated GIF or APNG straight to an animated GIF of its
+/// ASCII art rendered with `render`
+///
+/// `options` must use `ColorFormat::Plain`.
+#[wasm_bindgen]
+pub fn convert_animation_to_gif(
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    options: &ConvertOptions,
+    render: &RenderOptions,
+) -> Result<Vec<u8>, String> {
+    if options.color_format != ColorFormat::Plain {
+        return Err("GIF export needs plain text frames; set ColorFormat::Plain".to_string());
+    }
+    convert_animation(image_data, width, height, options)?.to_gif(render)
+}
+
+// Decode all frames as full-canvas RGBA images with delays in milliseconds
+//
+// Each frame is decoded within `limits`; the frames together must stay
+// under its allocation limit and `MAX_ANIMATION_FRAMES`.
+fn decode_frames(image_data: &[u8], limits: &DecodeLimits) -> Result<Vec<(RgbaImage, u32)>, String> {
+    let format = image::guess_format(image_data).map_err(|e| format!("Failed to detect image format: {}", e))?;
+
+    let frames = match format {
+        ImageFormat::Gif => {
+            let mut decoder = GifDecoder::new(Cursor::new(image_data))
+                .map_err(|e| format!("Failed to decode GIF: {}", e))?;
+            limit_decoder(&mut decoder, limits)?;
+            decoder.into_frames()
+        }
+        ImageFormat::Png => {
+            let mut decoder = PngDecoder::new(Cursor::new(image_data))
+                .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+            if !decoder.is_apng().map_err(|e| format!("Failed to decode PNG: {}", e))? {
+                return decode_still(image_data, limits);
+            }
+            limit_decoder(&mut decoder, limits)?;
+            decoder
+                .apng()
+                .map_err(|e| format!("Failed to decode APNG: {}", e))?
+                .into_frames()
+        }
+        _ => return decode_still(image_data, limits),
+    };
+
+    let mut decoded = Vec::new();
+    let mut total_bytes = 0u64;
+    for frame in frames {
+        let frame = frame.map_err(|e| match e {
+            image::ImageError::Limits(e) => format!("Animation frame exceeds decode limits: {}", e),
+            e => format!("Failed to decode animation frame: {}", e),
+        })?;
+        if decoded.len() == MAX_ANIMATION_FRAMES {
+            return Err(format!("Animation has more than {} frames", MAX_ANIMATION_FRAMES));
+        }
+        total_bytes += frame.buffer().as_raw().len() as u64;
+        if total_bytes > limits.max_alloc as u64 {
+            return Err(format!(
+                "Decoding the first {} frames needs {} bytes, over the {} byte limit",
+                decoded.len() + 1,
+                total_bytes,
+                limits.max_alloc as u64
+            ));
+        }
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        decoded.push((frame.into_buffer(), numer / denom.max(1)));
+    }
+
+    if decoded.is_empty() {
+        return Err("Animation has no frames".to_string());
+    }
+    Ok(decoded)
+}
+
+// Check the canvas against `limits` and have the decoder enforce them per frame
+fn limit_decoder(decoder: &mut impl ImageDecoder, limits: &DecodeLimits) -> Result<(), String> {
+    limits.check(decoder)?;
+    decoder
+        .set_limits(limits.to_image_limits())
+        .map_err(|e| format!("Image exceeds decode limits: {}", e))
+}
+
+fn decode_still(image_data: &[u8], limits: &DecodeLimits) -> Result<Vec<(RgbaImage, u32)>, String> {
+    Ok(vec![(decode_oriented(image_data, limits)?.to_rgba8(), 0)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GIF of 4x2 pixel frames alternating black and white
+    fn gif_of(frame_count: usize) -> Vec<u8> {
+        let frames = (0..frame_count).map(|i| {
+            let v = if i % 2 == 0 { 0 } else { 255 };
+            let buffer = RgbaImage::from_pixel(4, 2, image::Rgba([v, v, v, 255]));
+            Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(120, 1))
+        });
+        let mut output = Vec::new();
+        GifEncoder::new(&mut output).encode_frames(frames).unwrap();
+        output
+    }
+
+    // Two-frame GIF: black then white
+    fn blink_gif() -> Vec<u8> {
+        gif_of(2)
+    }
+
+    #[test]
+    fn test_converts_every_frame_with_delays() {
+        let options = ConvertOptions::new().with_charset(" #");
+        let animation = convert_animation(&blink_gif(), 0, 0, &options).unwrap();
+        assert_eq!(animation.frames(), vec!["    \n    \n", "####\n####\n"]);
+        assert_eq!(animation.delays(), vec![120, 120]);
+
+        let resized = convert_animation(&blink_gif(), 2, 1, &options).unwrap();
+        assert_eq!(resized.frame(1).unwrap(), "##\n");
+        assert_eq!(resized.frame(2), None);
+    }
+
+    #[test]
+    fn test_reencodes_as_animated_gif() {
+        let options = ConvertOptions::new().with_charset(" #");
+        let gif = convert_animation_to_gif(&blink_gif(), 2, 1, &options, &RenderOptions::new()).unwrap();
+        let frames = GifDecoder::new(Cursor::new(gif)).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].buffer().dimensions(), (12, 10));
+        assert_eq!(frames[1].delay().numer_denom_ms(), (120, 1));
+
+        let colored = options.with_color_format(ColorFormat::Html);
+        assert!(convert_animation_to_gif(&blink_gif(), 2, 1, &colored, &RenderOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_targets_and_animations() {
+        let options = ConvertOptions::new();
+        let err = convert_animation(&blink_gif(), 100_000, 100_000, &options).unwrap_err();
+        assert!(err.contains("got 100000x100000"), "{}", err);
+
+        assert!(decode_frames(&blink_gif(), &DecodeLimits::new().with_max_dimensions(2, 2)).is_err());
+        // Each 32-byte frame decodes within 64 bytes, but three don't fit together
+        let err = decode_frames(&gif_of(3), &DecodeLimits::new().with_max_alloc(64)).unwrap_err();
+        assert!(err.contains("first 3 frames"), "{}", err);
+
+        let err = convert_animation(&gif_of(MAX_ANIMATION_FRAMES + 1), 0, 0, &options).unwrap_err();
+        assert_eq!(err, "Animation has more than 1000 frames");
+    }
+}
@@ -0,0 +1,75 @@
+use crate::convert_options::ConvertOptions;
+
+/// Copy `data` into `out` with every pixel blended over `background` by its
+/// alpha
+///
+/// Alpha bytes are kept so later stages can still find transparent pixels.
+pub(crate) fn composite_over(data: &[u8], background: [u8; 3], out: &mut Vec<u8>) {
+    out.clear();
+    out.extend_from_slice(data);
+    for pixel in out.chunks_exact_mut(4).filter(|pixel| pixel[3] != 255) {
+        let alpha = pixel[3] as u32;
+        for (channel, &bg) in pixel[..3].iter_mut().zip(&background) {
+            *channel = ((*channel as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Mark the pixels to draw as spaces in `blank`, which is left empty when
+/// the options blank nothing
+///
+/// A pixel is blank when its alpha is below the alpha threshold, with
+/// `ignore_white` when its adjusted brightness in `gray` is 255, or, with
+/// background ignoring enabled, when every channel of the composited color
+/// is within the tolerance of the background color.
+pub(crate) fn blank_pixels(composited: &[u8], gray: &[f64], options: &ConvertOptions, blank: &mut Vec<bool>) {
+    blank.clear();
+    if options.alpha_threshold == 0 && !options.ignore_white && options.ignore_background.is_none() {
+        return;
+    }
+
+    let near_background = |pixel: &[u8]| {
+        options.ignore_background.is_some_and(|tolerance| {
+            pixel[..3]
+                .iter()
+                .zip(&options.background)
+                .all(|(&c, &bg)| (c as f64 - bg as f64).abs() <= tolerance)
+        })
+    };
+    blank.extend(composited.chunks_exact(4).zip(gray).map(|(pixel, &gray)| {
+        pixel[3] < options.alpha_threshold || (options.ignore_white && gray == 255.0) || near_background(pixel)
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_blends_by_alpha() {
+        let mut out = vec![1, 2, 3];
+        composite_over(&[10, 20, 30, 255], [255, 255, 255], &mut out);
+        assert_eq!(out, [10, 20, 30, 255]);
+
+        composite_over(&[255, 0, 0, 128, 0, 0, 0, 0], [0, 0, 255], &mut out);
+        assert_eq!(out, [128, 0, 127, 128, 0, 0, 255, 0]);
+    }
+
+    #[test]
+    fn test_blank_pixels_by_alpha_tolerance_and_white() {
+        let data = [250, 250, 250, 255, 200, 200, 200, 255, 0, 0, 0, 10];
+        let gray = [250.0, 255.0, 0.0];
+        let mut blank = vec![true];
+        blank_pixels(&data, &gray, &ConvertOptions::new(), &mut blank);
+        assert!(blank.is_empty());
+
+        let options = ConvertOptions::new().with_alpha_threshold(128).with_ignore_background(8.0);
+        blank_pixels(&data, &gray, &options, &mut blank);
+        assert_eq!(blank, [true, false, true]);
+
+        // Judged by the adjusted brightness, whatever the background
+        let white = ConvertOptions::new().with_ignore_white(true).with_background(0, 0, 0);
+        blank_pixels(&data, &gray, &white, &mut blank);
+        assert_eq!(blank, [false, true, false]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::rng::Rng;
+
+/// Side of the tileable blue-noise threshold map
+pub(crate) const BLUE_NOISE_SIZE: usize = 64;
+
+// Gaussian filter used to measure how crowded each pixel's neighborhood is
+const SIGMA: f32 = 1.5;
+// Weights past this radius are below 1e-3 and skipped
+const RADIUS: usize = 6;
+
+// Fraction of pixels set in the initial binary pattern
+const INITIAL_DENSITY: usize = 10;
+
+const LEN: usize = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+
+thread_local! {
+    // Map for the most recently used seed; generating one takes a while, and
+    // streams convert every frame with the same seed
+    static CACHE: RefCell<Option<(u32, Rc<Vec<usize>>)>> = const { RefCell::new(None) };
+}
+
+/// `blue_noise_ranks` for `seed`, generated once and reused while the seed
+/// stays the same
+pub(crate) fn cached_blue_noise_ranks(seed: u32) -> Rc<Vec<usize>> {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        match &*cache {
+            Some((cached_seed, ranks)) if *cached_seed == seed => ranks.clone(),
+            _ => {
+                let ranks = Rc::new(blue_noise_ranks(seed));
+                *cache = Some((seed, ranks.clone()));
+                ranks
+            }
+        }
+    })
+}
+
+/// Blue-noise threshold map generated with void-and-cluster
+///
+/// Returns `BLUE_NOISE_SIZE`² ranks, row-major, forming a permutation of
+/// `0..BLUE_NOISE_SIZE²`. Distances wrap around the edges, so the map tiles
+/// without seams. The same `seed` always yields the same map.
+pub(crate) fn blue_noise_ranks(seed: u32) -> Vec<usize> {
+    let weights = gaussian_weights();
+    let mut rng = Rng::new(seed as u64);
+    let mut pattern = vec![false; LEN];
+    let mut energy = vec![0.0f32; LEN];
+
+    // Random initial pattern
+    let mut ones = 0;
+    while ones < LEN / INITIAL_DENSITY {
+        let i = rng.below(LEN);
+        if !pattern[i] {
+            pattern[i] = true;
+            splat(&mut energy, &weights, i, 1.0);
+            ones += 1;
+        }
+    }
+
+    // Move points from the tightest cluster to the largest void until the
+    // pattern is evenly spread
+    for _ in 0..LEN {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        splat(&mut energy, &weights, cluster, -1.0);
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, &weights, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; LEN];
+
+    // Phase 1: rank the initial points by removing clusters
+    let mut removed = pattern.clone();
+    let mut removed_energy = energy.clone();
+    for rank in (0..ones).rev() {
+        let cluster = tightest_cluster(&removed, &removed_energy);
+        removed[cluster] = false;
+        splat(&mut removed_energy, &weights, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // Phase 2: fill the remaining pixels void by void
+    for rank in ones..LEN {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, &weights, void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks
+}
+
+fn gaussian_weights() -> Vec<f32> {
+    let side = 2 * RADIUS + 1;
+    (0..side * side)
+        .map(|i| {
+            let dx = (i % side) as f32 - RADIUS as f32;
+            let dy = (i / side) as f32 - RADIUS as f32;
+            (-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp()
+        })
+        .collect()
+}
+
+// Add (or remove, with a negative sign) one point's contribution to the
+// energy of its toroidal neighborhood
+fn splat(energy: &mut [f32], weights: &[f32], idx: usize, sign: f32) {
+    let side = 2 * RADIUS + 1;
+    let (x, y) = (idx % BLUE_NOISE_SIZE, idx / BLUE_NOISE_SIZE);
+    for wy in 0..side {
+        let ny = (y + BLUE_NOISE_SIZE + wy - RADIUS) % BLUE_NOISE_SIZE;
+        for wx in 0..side {
+            let nx = (x + BLUE_NOISE_SIZE + wx - RADIUS) % BLUE_NOISE_SIZE;
+            energy[ny * BLUE_NOISE_SIZE + nx] += sign * weights[wy * side + wx];
+        }
+    }
+}
+
+// Set pixel with the most set neighbors
+fn tightest_cluster(pattern: &[bool], energy: &[f32]) -> usize {
+    let mut best = 0;
+    let mut best_energy = f32::NEG_INFINITY;
+    for (i, (&set, &e)) in pattern.iter().zip(energy).enumerate() {
+        if set && e > best_energy {
+            best = i;
+            best_energy = e;
+        }
+    }
+    best
+}
+
+// Unset pixel with the fewest set neighbors
+fn largest_void(pattern: &[bool], energy: &[f32]) -> usize {
+    let mut best = 0;
+    let mut best_energy = f32::INFINITY;
+    for (i, (&set, &e)) in pattern.iter().zip(energy).enumerate() {
+        if !set && e < best_energy {
+            best = i;
+            best_energy = e;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranks_are_a_reproducible_permutation() {
+        let ranks = blue_noise_ranks(7);
+        assert_eq!(ranks, blue_noise_ranks(7));
+        assert_ne!(ranks, blue_noise_ranks(8));
+        let mut sorted = ranks.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..LEN).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sparse_thresholds_are_evenly_spread() {
+        // The lowest ranks must not clump: with 1/16 of the pixels set, no
+        // two of them may be neighbors, even across the wrap-around edge
+        let ranks = blue_noise_ranks(1);
+        let lit: Vec<usize> = (0..LEN).filter(|&i| ranks[i] < LEN / 16).collect();
+        let wrap = |a: usize, b: usize| {
+            let d = a.abs_diff(b);
+            d.min(BLUE_NOISE_SIZE - d)
+        };
+        for (n, &a) in lit.iter().enumerate() {
+            for &b in &lit[n + 1..] {
+                let dx = wrap(a % BLUE_NOISE_SIZE, b % BLUE_NOISE_SIZE);
+                let dy = wrap(a / BLUE_NOISE_SIZE, b / BLUE_NOISE_SIZE);
+                assert!(dx.max(dy) > 1, "{} and {} touch", a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use wasm_bindgen::prelude::*;
+
+use crate::font::{cell_mask, CELL_HEIGHT, CELL_WIDTH};
+
+// Coverage of block elements the bitmap font does not include
+const BLOCK_COVERAGE: &[(char, f32)] = &[('░', 0.25), ('▒', 0.5), ('▓', 0.75), ('█', 1.0)];
+
+/// Fraction of a character cell covered by ink, from the embedded font
+pub(crate) fn ink_coverage(c: char) -> Option<f32> {
+    if let Some(mask) = cell_mask(c) {
+        let inked = mask.iter().filter(|&&ink| ink).count();
+        return Some(inked as f32 / (CELL_WIDTH * CELL_HEIGHT) as f32);
+    }
+    BLOCK_COVERAGE
+        .iter()
+        .find(|(ch, _)| *ch == c)
+        .map(|&(_, coverage)| coverage)
+}
+
+/// Sort a character set by ink coverage and remove duplicates
+///
+/// The result runs from the least to the most inked glyph, which is the
+/// darkest-to-brightest order `convert_to_ascii` expects. Characters with
+/// equal coverage keep their input order.
+///
+/// # Arguments
+/// * `charset` - Characters in any order; printable ASCII and `░▒▓█`
+/// * `steps` - When 2 or more, resample to at most this many glyphs whose
+///   coverages are as evenly spaced as possible; 0 keeps every glyph
+///
+/// # Returns
+/// The normalized charset
+#[wasm_bindgen]
+pub fn calibrate_charset(charset: &str, steps: usize) -> Result<String, String> {
+    let mut glyphs: Vec<(char, f32)> = Vec::new();
+    for c in charset.chars() {
+        if glyphs.iter().any(|&(seen, _)| seen == c) {
+            continue;
+        }
+        let coverage = ink_coverage(c)
+            .ok_or_else(|| format!("Cannot measure ink coverage of {:?}: not in the embedded font", c))?;
+        glyphs.push((c, coverage));
+    }
+
+    if glyphs.is_empty() {
+        return Err("Charset must contain at least one character".to_string());
+    }
+    if steps == 1 {
+        return Err("Resampling needs at least 2 steps, or 0 to keep every glyph".to_string());
+    }
+
+    glyphs.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    if steps >= 2 && steps < glyphs.len() {
+        glyphs = resample(&glyphs, steps);
+    }
+
+    Ok(glyphs.into_iter().map(|(c, _)| c).collect())
+}
+
+// Pick the glyph nearest to each of `steps` evenly spaced coverages
+//
+// `glyphs` must be sorted by coverage. The lowest and highest coverages are
+// always represented; a glyph picked for two neighboring targets appears once.
+fn resample(glyphs: &[(char, f32)], steps: usize) -> Vec<(char, f32)> {
+    let min = glyphs[0].1;
+    let max = glyphs[glyphs.len() - 1].1;
+    let mut picked: Vec<(char, f32)> = Vec::with_capacity(steps);
+
+    for i in 0..steps {
+        let target = min + (max - min) * i as f32 / (steps - 1) as f32;
+        let nearest = glyphs
+            .iter()
+            .min_by(|a, b| (a.1 - target).abs().total_cmp(&(b.1 - target).abs()))
+            .copied();
+        if let Some(glyph) = nearest {
+            if picked.last().map(|&(c, _)| c) != Some(glyph.0) {
+                picked.push(glyph);
+            }
+        }
+    }
+
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibrate_sorts_and_dedupes() {
+        assert_eq!(calibrate_charset("@. #.@", 0).unwrap(), " .@#");
+        assert_eq!(calibrate_charset("█ ▒░▓", 0).unwrap(), " ░▒▓█");
+    }
+
+    #[test]
+    fn test_calibrate_resamples_to_even_steps() {
+        let full = calibrate_charset(" .'`^\",:;Il!i><~+_-?][}{1)(|/tfjrxnuvczXYUJCLQ0OZmwqpdbkhao*#MW&8%B@$", 0).unwrap();
+        let resampled = calibrate_charset(&full, 5).unwrap();
+        assert_eq!(resampled.chars().count(), 5);
+        let coverage = |s: &str, last: bool| {
+            let c = if last { s.chars().last() } else { s.chars().next() };
+            ink_coverage(c.unwrap())
+        };
+        assert_eq!(coverage(&resampled, false), coverage(&full, false));
+        assert_eq!(coverage(&resampled, true), coverage(&full, true));
+    }
+
+    #[test]
+    fn test_calibrate_rejects_unknown_glyphs() {
+        assert!(calibrate_charset("ab€", 0).is_err());
+        assert!(calibrate_charset("", 0).is_err());
+        assert!(calibrate_charset("ab", 1).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use wasm_bindgen::prelude::*;
+use std::fmt::Write;
+
+/// Output format for colored ASCII art
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    /// Plain text, no color information
+    Plain,
+    /// ANSI 24-bit truecolor escape sequences
+    AnsiTrueColor,
+    /// ANSI 256-color palette escape sequences
+    Ansi256,
+    /// HTML `<span style="color:...">` markup
+    Html,
+}
+
+impl ColorFormat {
+    pub(crate) fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "plain" | "none" | "" => Ok(Self::Plain),
+            "ansi" | "truecolor" | "24bit" => Ok(Self::AnsiTrueColor),
+            "ansi256" | "256" => Ok(Self::Ansi256),
+            "html" => Ok(Self::Html),
+            _ => Err(format!(
+                "Unsupported color format: {}. Use 'plain', 'ansi', 'ansi256' or 'html'",
+                s
+            )),
+        }
+    }
+}
+
+/// Average the RGB values of the source image over a grid of cells, into
+/// `colors`
+///
+/// Each cell covers `cell_w` x `cell_h` source pixels; partial cells at the
+/// right and bottom edges average only the pixels they actually cover.
+pub(crate) fn average_cell_colors(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    cell_w: usize,
+    cell_h: usize,
+    colors: &mut Vec<[u8; 3]>,
+) {
+    let cols = width.div_ceil(cell_w);
+    let rows = height.div_ceil(cell_h);
+    colors.clear();
+
+    for row in 0..rows {
+        let y0 = row * cell_h;
+        let y1 = (y0 + cell_h).min(height);
+        for col in 0..cols {
+            let x0 = col * cell_w;
+            let x1 = (x0 + cell_w).min(width);
+            let mut sum = [0u32; 3];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let idx = (y * width + x) * 4;
+                    sum[0] += data[idx] as u32;
+                    sum[1] += data[idx + 1] as u32;
+                    sum[2] += data[idx + 2] as u32;
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)).max(1) as u32;
+            colors.push([
+                ((sum[0] + count / 2) / count) as u8,
+                ((sum[1] + count / 2) / count) as u8,
+                ((sum[2] + count / 2) / count) as u8,
+            ]);
+        }
+    }
+}
+
+// Levels of the 6x6x6 color cube used by the xterm 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// Index of the nearest cube level for a single channel
+fn nearest_cube_index(v: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &level)| (level as i32 - v as i32).abs())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn distance_sq(a: [u8; 3], b: [u8; 3]) -> i32 {
+    let dr = a[0] as i32 - b[0] as i32;
+    let dg = a[1] as i32 - b[1] as i32;
+    let db = a[2] as i32 - b[2] as i32;
+    dr * dr + dg * dg + db * db
+}
+
+/// Map an RGB color to the closest entry of the xterm 256-color palette
+///
+/// Only the color cube (16-231) and the grayscale ramp (232-255) are
+/// considered, since the first 16 entries are redefined by most terminals.
+pub(crate) fn rgb_to_ansi256(rgb: [u8; 3]) -> u8 {
+    let (ri, gi, bi) = (
+        nearest_cube_index(rgb[0]),
+        nearest_cube_index(rgb[1]),
+        nearest_cube_index(rgb[2]),
+    );
+    let cube_color = [CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]];
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    // Grayscale ramp: 232 + i has level 8 + 10 * i
+    let avg = (rgb[0] as u32 + rgb[1] as u32 + rgb[2] as u32) / 3;
+    let gray_step = ((avg.saturating_sub(8) + 5) / 10).min(23);
+    let gray_level = (8 + 10 * gray_step) as u8;
+    let gray_color = [gray_level, gray_level, gray_level];
+
+    if distance_sq(rgb, gray_color) < distance_sq(rgb, cube_color) {
+        232 + gray_step as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+// Escape characters that have a meaning in HTML
+fn push_html_char(out: &mut String, c: char) {
+    match c {
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '&' => out.push_str("&amp;"),
+        '"' => out.push_str("&quot;"),
+        _ => out.push(c),
+    }
+}
+
+// Write the sequence that switches to `fg`, and to the `bg` background if any
+fn open_color(out: &mut String, fg: [u8; 3], bg: Option<[u8; 3]>, format: ColorFormat) {
+    let [r, g, b] = fg;
+    // Writing to a String cannot fail
+    let _ = match (format, bg) {
+        (ColorFormat::AnsiTrueColor, None) => write!(out, "\x1b[38;2;{};{};{}m", r, g, b),
+        (ColorFormat::AnsiTrueColor, Some([br, bg, bb])) => {
+            write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m", r, g, b, br, bg, bb)
+        }
+        (ColorFormat::Ansi256, None) => write!(out, "\x1b[38;5;{}m", rgb_to_ansi256(fg)),
+        (ColorFormat::Ansi256, Some(bg)) => {
+            write!(out, "\x1b[38;5;{};48;5;{}m", rgb_to_ansi256(fg), rgb_to_ansi256(bg))
+        }
+        (ColorFormat::Html, None) => write!(out, "<span style=\"color:#{:02x}{:02x}{:02x}\">", r, g, b),
+        (ColorFormat::Html, Some([br, bg, bb])) => write!(
+            out,
+            "<span style=\"color:#{:02x}{:02x}{:02x};background:#{:02x}{:02x}{:02x}\">",
+            r, g, b, br, bg, bb
+        ),
+        (ColorFormat::Plain, _) => Ok(()),
+    };
+}
+
+// Write the sequence that ends the current color run
+fn close_color(out: &mut String, format: ColorFormat) {
+    match format {
+        ColorFormat::AnsiTrueColor | ColorFormat::Ansi256 => out.push_str("\x1b[0m"),
+        ColorFormat::Html => out.push_str("</span>"),
+        ColorFormat::Plain => {}
+    }
+}
+
+/// Render a grid of characters with one color per cell
+///
+/// Consecutive cells with the same colors share a single escape sequence or
+/// `<span>`. Spaces are never colored, so blank cells show whatever is behind
+/// the text. `colors` must hold one entry per cell; for `ColorFormat::Plain`
+/// it may be empty. `backgrounds` is either empty or also one entry per cell.
+pub(crate) fn render_colored(
+    cells: &[char],
+    colors: &[[u8; 3]],
+    backgrounds: &[[u8; 3]],
+    cols: usize,
+    rows: usize,
+    format: ColorFormat,
+) -> String {
+    let mut out = String::with_capacity(cells.len() * 2);
+
+    for row in 0..rows {
+        let mut current: Option<([u8; 3], Option<[u8; 3]>)> = None;
+        for col in 0..cols {
+            let idx = row * cols + col;
+            let c = cells[idx];
+            let bg = backgrounds.get(idx).copied();
+
+            if c == ' ' {
+                // Spaces are always uncolored: invisible in any foreground
+                // color, and they end a run that draws a background
+                if current.is_some_and(|(_, bg)| bg.is_some()) {
+                    close_color(&mut out, format);
+                    current = None;
+                }
+            } else if format != ColorFormat::Plain && current != Some((colors[idx], bg)) {
+                if current.is_some() {
+                    close_color(&mut out, format);
+                }
+                open_color(&mut out, colors[idx], bg, format);
+                current = Some((colors[idx], bg));
+            }
+
+            if format == ColorFormat::Html {
+                push_html_char(&mut out, c);
+            } else {
+                out.push(c);
+            }
+        }
+        if current.is_some() {
+            close_color(&mut out, format);
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi256_maps_primaries_and_grays() {
+        assert_eq!(rgb_to_ansi256([255, 0, 0]), 196);
+        assert_eq!(rgb_to_ansi256([0, 0, 0]), 16);
+        assert_eq!(rgb_to_ansi256([128, 128, 128]), 244);
+    }
+
+    #[test]
+    fn test_average_cell_colors_handles_partial_cells() {
+        // 3x1 image: red, blue, green
+        let data = [255, 0, 0, 255, 0, 0, 255, 255, 0, 255, 0, 255];
+        let mut colors = Vec::new();
+        average_cell_colors(&data, 3, 1, 2, 1, &mut colors);
+        assert_eq!(colors, vec![[128, 0, 128], [0, 255, 0]]);
+    }
+
+    #[test]
+    fn test_render_colored_merges_runs() {
+        let cells = ['#', '#', ' ', '<'];
+        let colors = [[1, 2, 3], [1, 2, 3], [9, 9, 9], [255, 0, 0]];
+        let html = render_colored(&cells, &colors, &[], 4, 1, ColorFormat::Html);
+        assert_eq!(
+            html,
+            "<span style=\"color:#010203\">## </span><span style=\"color:#ff0000\">&lt;</span>\n"
+        );
+        let ansi = render_colored(&cells, &colors, &[], 4, 1, ColorFormat::AnsiTrueColor);
+        assert_eq!(ansi, "\x1b[38;2;1;2;3m## \x1b[0m\x1b[38;2;255;0;0m<\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_render_colored_with_backgrounds() {
+        let cells = ['▀', '▀'];
+        let colors = [[255, 0, 0], [255, 0, 0]];
+        let backgrounds = [[0, 0, 255], [0, 0, 255]];
+        let ansi = render_colored(&cells, &colors, &backgrounds, 2, 1, ColorFormat::AnsiTrueColor);
+        assert_eq!(ansi, "\x1b[38;2;255;0;0;48;2;0;0;255m▀▀\x1b[0m\n");
+    }
+}
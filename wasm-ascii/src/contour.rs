@@ -0,0 +1,397 @@
+use crate::convert_options::ConvertOptions;
+
+/// Reusable scratch memory for the contour pipeline
+///
+/// Every image buffer is a flat row-major `f32` slice of `width * height`
+/// pixels. Buffers are resized in place, so repeated conversions of the same
+/// size do not allocate after the first one.
+#[derive(Debug, Default)]
+pub(crate) struct ContourBuffers {
+    gray: Vec<f32>,
+    dog: Vec<f32>,
+    blur: Vec<f32>,
+    tmp: Vec<f32>,
+    mag: Vec<f32>,
+    angle: Vec<f32>,
+    suppressed: Vec<f32>,
+    kernel1: Vec<f32>,
+    kernel2: Vec<f32>,
+    edges: Vec<bool>,
+    stack: Vec<usize>,
+}
+
+impl ContourBuffers {
+    // Zero every image buffer and size it for `len` pixels
+    fn reset(&mut self, len: usize) {
+        for buf in [
+            &mut self.gray,
+            &mut self.dog,
+            &mut self.blur,
+            &mut self.tmp,
+            &mut self.mag,
+            &mut self.angle,
+            &mut self.suppressed,
+        ] {
+            buf.clear();
+            buf.resize(len, 0.0);
+        }
+        self.edges.clear();
+        self.edges.resize(len, false);
+    }
+}
+
+// Fill `kernel` with a normalized 1D Gaussian
+//
+// The outer product of this kernel with itself equals the normalized 2D
+// Gaussian, which is what makes the blur separable.
+fn gaussian_kernel_1d(kernel: &mut Vec<f32>, sigma: f32, kernel_size: usize) {
+    let half = (kernel_size / 2) as i32;
+    kernel.clear();
+    kernel.extend((0..kernel_size as i32).map(|i| {
+        let d = (i - half) as f32;
+        (-(d * d) / (2.0 * sigma * sigma)).exp()
+    }));
+    let sum: f32 = kernel.iter().sum();
+    for value in kernel.iter_mut() {
+        *value /= sum;
+    }
+}
+
+// Blur `src` into `dst` with a horizontal then a vertical pass
+//
+// Pixels outside the image count as zero, matching a full 2D convolution
+// with zero padding.
+fn convolve_separable(
+    src: &[f32],
+    dst: &mut [f32],
+    tmp: &mut [f32],
+    width: usize,
+    height: usize,
+    kernel: &[f32],
+) {
+    let half = kernel.len() / 2;
+
+    // Horizontal pass
+    for (src_row, tmp_row) in src.chunks_exact(width).zip(tmp.chunks_exact_mut(width)) {
+        for (x, out) in tmp_row.iter_mut().enumerate() {
+            let mut sum = 0.0;
+            for (k, &weight) in kernel.iter().enumerate() {
+                if let Some(xx) = (x + k).checked_sub(half).filter(|&xx| xx < width) {
+                    sum += src_row[xx] * weight;
+                }
+            }
+            *out = sum;
+        }
+    }
+
+    // Vertical pass
+    for y in 0..height {
+        let dst_row = &mut dst[y * width..(y + 1) * width];
+        dst_row.fill(0.0);
+        for (k, &weight) in kernel.iter().enumerate() {
+            if let Some(yy) = (y + k).checked_sub(half).filter(|&yy| yy < height) {
+                let tmp_row = &tmp[yy * width..(yy + 1) * width];
+                for (out, &value) in dst_row.iter_mut().zip(tmp_row) {
+                    *out += value * weight;
+                }
+            }
+        }
+    }
+}
+
+// Difference of Gaussians of `buffers.gray` into `buffers.dog`
+fn difference_of_gaussians(
+    buffers: &mut ContourBuffers,
+    width: usize,
+    height: usize,
+    sigma1: f32,
+    sigma2: f32,
+    kernel_size: usize,
+) {
+    gaussian_kernel_1d(&mut buffers.kernel1, sigma1, kernel_size);
+    gaussian_kernel_1d(&mut buffers.kernel2, sigma2, kernel_size);
+
+    convolve_separable(&buffers.gray, &mut buffers.dog, &mut buffers.tmp, width, height, &buffers.kernel1);
+    convolve_separable(&buffers.gray, &mut buffers.blur, &mut buffers.tmp, width, height, &buffers.kernel2);
+
+    for (d, &b) in buffers.dog.iter_mut().zip(&buffers.blur) {
+        *d -= b;
+    }
+}
+
+// Turn the plain DoG in `buffers.dog` into an XDoG line drawing (0-255)
+//
+// `buffers.blur` still holds the wide blur, so the narrow one is recovered
+// as `dog + blur` and the sharpened image is `G1 + tau * (G1 - G2)`.
+fn apply_xdog(buffers: &mut ContourBuffers, tau: f32, phi: f32, epsilon: f32) {
+    for (d, &wide) in buffers.dog.iter_mut().zip(&buffers.blur) {
+        let narrow = *d + wide;
+        let sharpened = (narrow + tau * *d) / 255.0;
+        let toned = if sharpened >= epsilon {
+            1.0
+        } else {
+            1.0 + (phi * (sharpened - epsilon)).tanh()
+        };
+        *d = toned * 255.0;
+    }
+}
+
+// Canny-style edge linking into `buffers.edges`
+//
+// Pixels above `high` seed the edges, which then grow through 8-connected
+// neighbors above `low`.
+fn hysteresis(buffers: &mut ContourBuffers, width: usize, height: usize, low: f32, high: f32) {
+    let ContourBuffers { suppressed, edges, stack, .. } = buffers;
+    stack.clear();
+
+    for (idx, &mag) in suppressed.iter().enumerate() {
+        if mag > high {
+            edges[idx] = true;
+            stack.push(idx);
+        }
+    }
+
+    while let Some(idx) = stack.pop() {
+        let (x, y) = (idx % width, idx / width);
+        for ny in y.saturating_sub(1)..(y + 2).min(height) {
+            for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                let n = ny * width + nx;
+                if !edges[n] && suppressed[n] > low {
+                    edges[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+    }
+}
+
+// Sobel gradient magnitude and orientation (0-180 degrees) of `src`
+//
+// Border pixels are left at zero.
+fn apply_sobel(src: &[f32], mag: &mut [f32], angle: &mut [f32], width: usize, height: usize) {
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let idx = y * width + x;
+            let a = src[idx - width - 1];
+            let b = src[idx - width];
+            let c = src[idx - width + 1];
+            let d = src[idx - 1];
+            let f = src[idx + 1];
+            let g = src[idx + width - 1];
+            let h = src[idx + width];
+            let i = src[idx + width + 1];
+
+            let gx = (c - a) + 2.0 * (f - d) + (i - g);
+            let gy = (g - a) + 2.0 * (h - b) + (i - c);
+
+            mag[idx] = (gx * gx + gy * gy).sqrt();
+            let mut theta = gy.atan2(gx).to_degrees();
+            if theta < 0.0 {
+                theta += 180.0;
+            }
+            angle[idx] = theta;
+        }
+    }
+}
+
+// Keep only pixels that are local maxima along their gradient direction
+fn non_max_suppression(
+    mag: &[f32],
+    angle: &[f32],
+    suppressed: &mut [f32],
+    width: usize,
+    height: usize,
+) {
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let idx = y * width + x;
+            let current_mag = mag[idx];
+            let theta = angle[idx];
+            let (neighbor1, neighbor2) = if !(22.5..157.5).contains(&theta) {
+                // 0° direction
+                (mag[idx - 1], mag[idx + 1])
+            } else if theta < 67.5 {
+                // 45° direction
+                (mag[idx - width + 1], mag[idx + width - 1])
+            } else if theta < 112.5 {
+                // 90° direction
+                (mag[idx - width], mag[idx + width])
+            } else {
+                // 135° direction
+                (mag[idx - width - 1], mag[idx + width + 1])
+            };
+
+            suppressed[idx] = if current_mag >= neighbor1 && current_mag >= neighbor2 {
+                current_mag
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+// Directional glyph for a gradient orientation in degrees
+pub(crate) fn edge_glyph(angle: f32) -> char {
+    let adjusted_angle = (angle + 90.0) % 180.0;
+    if !(22.5..157.5).contains(&adjusted_angle) {
+        '-'
+    } else if adjusted_angle < 67.5 {
+        '/'
+    } else if adjusted_angle < 112.5 {
+        '|'
+    } else {
+        '\\'
+    }
+}
+
+/// Generate contour ASCII cells using DoG, Sobel and non-maximum suppression
+///
+/// `gray` is the adjusted grayscale image. `cells` is cleared and filled
+/// with one character per pixel.
+pub(crate) fn generate_contour_cells(
+    gray: &[f64],
+    width: usize,
+    height: usize,
+    options: &ConvertOptions,
+    buffers: &mut ContourBuffers,
+    cells: &mut Vec<char>,
+) {
+    buffers.reset(width * height);
+
+    for (dst, &v) in buffers.gray.iter_mut().zip(gray) {
+        *dst = v as f32;
+    }
+
+    // Apply DoG, optionally sharpened into XDoG
+    let sigma = options.dog_sigma as f32;
+    difference_of_gaussians(
+        buffers,
+        width,
+        height,
+        sigma,
+        sigma * options.dog_k as f32,
+        options.dog_kernel_size,
+    );
+    if options.xdog_tau > 0.0 {
+        apply_xdog(
+            buffers,
+            options.xdog_tau as f32,
+            options.xdog_phi as f32,
+            options.xdog_epsilon as f32,
+        );
+    }
+
+    // Apply Sobel
+    apply_sobel(&buffers.dog, &mut buffers.mag, &mut buffers.angle, width, height);
+
+    // Non-maximum suppression
+    non_max_suppression(&buffers.mag, &buffers.angle, &mut buffers.suppressed, width, height);
+
+    // Link edges; a missing low threshold degrades to a single cut
+    let high = options.dog_threshold as f32;
+    let low = options.dog_low_threshold.map_or(high, |low| (low as f32).min(high));
+    hysteresis(buffers, width, height, low, high);
+
+    // Generate ASCII cells
+    cells.clear();
+    cells.extend(buffers.edges.iter().zip(&buffers.angle).map(|(&edge, &angle)| {
+        if edge {
+            edge_glyph(angle)
+        } else {
+            ' '
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference DoG with a full 2D kernel in f64, as the pipeline used to compute it
+    fn reference_dog(gray: &[f64], width: usize, height: usize, s1: f64, s2: f64) -> Vec<f64> {
+        let blur = |sigma: f64| {
+            let mut kernel = [[0.0; 3]; 3];
+            let mut sum = 0.0;
+            for (ky, row) in kernel.iter_mut().enumerate() {
+                for (kx, value) in row.iter_mut().enumerate() {
+                    let (dy, dx) = (ky as f64 - 1.0, kx as f64 - 1.0);
+                    *value = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+                    sum += *value;
+                }
+            }
+            let mut out = vec![0.0; width * height];
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    let mut acc = 0.0;
+                    for ky in -1..=1 {
+                        for kx in -1..=1 {
+                            let (yy, xx) = (y + ky, x + kx);
+                            if yy >= 0 && yy < height as i32 && xx >= 0 && xx < width as i32 {
+                                acc += gray[(yy * width as i32 + xx) as usize]
+                                    * kernel[(ky + 1) as usize][(kx + 1) as usize]
+                                    / sum;
+                            }
+                        }
+                    }
+                    out[(y * width as i32 + x) as usize] = acc;
+                }
+            }
+            out
+        };
+        blur(s1).iter().zip(blur(s2)).map(|(a, b)| a - b).collect()
+    }
+
+    #[test]
+    fn test_separable_dog_matches_2d_reference() {
+        let (width, height) = (13, 9);
+        let gray: Vec<f64> = (0..width * height)
+            .map(|i| ((i * 37 + (i / width) * 11) % 256) as f64)
+            .collect();
+
+        let mut buffers = ContourBuffers::default();
+        buffers.reset(width * height);
+        for (dst, &src) in buffers.gray.iter_mut().zip(&gray) {
+            *dst = src as f32;
+        }
+        difference_of_gaussians(&mut buffers, width, height, 0.5, 1.0, 3);
+
+        let expected = reference_dog(&gray, width, height, 0.5, 1.0);
+        for (&actual, &expected) in buffers.dog.iter().zip(&expected) {
+            assert!((actual as f64 - expected).abs() < 1e-3, "{} vs {}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_contour_draws_vertical_edge() {
+        // Left half black, right half white
+        let (width, height) = (8, 9);
+        let gray: Vec<f64> = (0..width * height)
+            .map(|i| if i % width < width / 2 { 0.0 } else { 255.0 })
+            .collect();
+        let options = ConvertOptions::new().with_dog_threshold(10.0);
+        let mut cells = Vec::new();
+        generate_contour_cells(&gray, width, height, &options, &mut ContourBuffers::default(), &mut cells);
+
+        // Zero padding also creates edges along the image border, so only
+        // look at a row away from the top and bottom
+        assert_eq!(cells.len(), width * height);
+        let middle = &cells[4 * width..5 * width];
+        assert!(middle.contains(&'|'));
+        assert!(middle.iter().all(|&c| c == '|' || c == ' '));
+    }
+
+    #[test]
+    fn test_hysteresis_keeps_weak_edges_connected_to_strong_ones() {
+        let (width, height) = (5, 1);
+        let mut buffers = ContourBuffers::default();
+        buffers.reset(width * height);
+        buffers.suppressed.copy_from_slice(&[50.0, 200.0, 50.0, 0.0, 50.0]);
+
+        hysteresis(&mut buffers, width, height, 40.0, 100.0);
+        assert_eq!(buffers.edges, vec![true, true, true, false, false]);
+
+        buffers.edges.fill(false);
+        hysteresis(&mut buffers, width, height, 100.0, 100.0);
+        assert_eq!(buffers.edges, vec![false, true, false, false, false]);
+    }
+}
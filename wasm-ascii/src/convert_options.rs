@@ -0,0 +1,529 @@
+use wasm_bindgen::prelude::*;
+
+use crate::color_output::ColorFormat;
+use crate::glyph_match::GlyphMetric;
+use crate::tone::Equalization;
+
+/// Edge detection method applied before character mapping
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMethod {
+    /// Plain luminance mapping
+    None,
+    /// Binary Sobel edges replace the grayscale image
+    Sobel,
+    /// Difference of Gaussians contours drawn with directional glyphs
+    Dog,
+    /// Brightness fill with DoG contour glyphs drawn on top
+    Hybrid,
+}
+
+impl EdgeMethod {
+    pub(crate) fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "none" | "" => Ok(Self::None),
+            "sobel" => Ok(Self::Sobel),
+            "dog" => Ok(Self::Dog),
+            "hybrid" => Ok(Self::Hybrid),
+            _ => Err(format!(
+                "Unsupported edge method: {}. Use 'none', 'sobel', 'dog' or 'hybrid'",
+                s
+            )),
+        }
+    }
+}
+
+/// Dithering algorithm used to distribute quantization error
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DitherAlgorithm {
+    /// No dithering
+    None,
+    FloydSteinberg,
+    Atkinson,
+    /// Seeded white noise
+    Noise,
+    /// 4x4 Bayer matrix
+    Ordered,
+    JarvisJudiceNinke,
+    Stucki,
+    Burkes,
+    Sierra,
+    SierraTwoRow,
+    SierraLite,
+    /// 8x8 Bayer matrix
+    Ordered8x8,
+    /// 16x16 Bayer matrix
+    Ordered16x16,
+    /// Seeded 64x64 void-and-cluster blue-noise threshold map
+    BlueNoise,
+}
+
+impl DitherAlgorithm {
+    pub(crate) fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "none" | "" => Ok(Self::None),
+            "floyd" | "floyd-steinberg" => Ok(Self::FloydSteinberg),
+            "atkinson" => Ok(Self::Atkinson),
+            "noise" | "white-noise" => Ok(Self::Noise),
+            "blue-noise" => Ok(Self::BlueNoise),
+            "ordered" | "bayer" => Ok(Self::Ordered),
+            "jarvis" | "jjn" => Ok(Self::JarvisJudiceNinke),
+            "stucki" => Ok(Self::Stucki),
+            "burkes" => Ok(Self::Burkes),
+            "sierra" => Ok(Self::Sierra),
+            "sierra2" | "sierra-two-row" => Ok(Self::SierraTwoRow),
+            "sierra-lite" => Ok(Self::SierraLite),
+            "ordered8" => Ok(Self::Ordered8x8),
+            "ordered16" => Ok(Self::Ordered16x16),
+            _ => Err(format!(
+                "Unsupported dither algorithm: {}. Use 'floyd', 'atkinson', 'jarvis', 'stucki', \
+                 'burkes', 'sierra', 'sierra2', 'sierra-lite', 'noise', 'blue-noise', 'ordered', 'ordered8' or 'ordered16'",
+                s
+            )),
+        }
+    }
+}
+
+/// How pixels are turned into characters
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// One character per pixel, chosen by brightness
+    Luminance,
+    /// One character per cell, chosen by comparing the cell with glyph shapes
+    GlyphMatch,
+    /// Braille dots, 2x4 pixels per character
+    Braille,
+    /// Upper/lower half blocks, 1x2 pixels per character
+    HalfBlock,
+    /// Quadrant blocks, 2x2 pixels per character
+    Quadrant,
+    /// Sextant blocks, 2x3 pixels per character
+    Sextant,
+}
+
+impl RenderMode {
+    // Modes that pack several thresholded pixels into each character
+    pub(crate) fn is_subcell(self) -> bool {
+        matches!(self, Self::Braille | Self::HalfBlock | Self::Quadrant | Self::Sextant)
+    }
+}
+
+// Largest supported DoG kernel, keeps the blur cost bounded
+const MAX_DOG_KERNEL_SIZE: usize = 31;
+
+/// Options for `convert_with_options`
+///
+/// Built from JavaScript with chained setters, e.g.
+/// `new ConvertOptions().with_charset(" .:#").with_invert(true)`.
+/// Each setter consumes the options and returns the updated value.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub(crate) charset: String,
+    pub(crate) invert: bool,
+    pub(crate) ignore_white: bool,
+    pub(crate) background: [u8; 3],
+    pub(crate) alpha_threshold: u8,
+    pub(crate) ignore_background: Option<f64>,
+    pub(crate) dither_algorithm: DitherAlgorithm,
+    pub(crate) serpentine: bool,
+    pub(crate) seed: u32,
+    pub(crate) edge_method: EdgeMethod,
+    pub(crate) edge_threshold: f64,
+    pub(crate) dog_threshold: f64,
+    pub(crate) dog_low_threshold: Option<f64>,
+    pub(crate) dog_sigma: f64,
+    pub(crate) dog_k: f64,
+    pub(crate) dog_kernel_size: usize,
+    pub(crate) xdog_tau: f64,
+    pub(crate) xdog_phi: f64,
+    pub(crate) xdog_epsilon: f64,
+    pub(crate) brightness: f64,
+    pub(crate) contrast: f64,
+    pub(crate) gamma: f64,
+    pub(crate) equalization: Equalization,
+    pub(crate) clahe_tile_size: usize,
+    pub(crate) clahe_clip_limit: f64,
+    pub(crate) auto_levels: f64,
+    pub(crate) color_format: ColorFormat,
+    pub(crate) render_mode: RenderMode,
+    pub(crate) glyph_metric: GlyphMetric,
+    pub(crate) cell_width: usize,
+    pub(crate) cell_height: usize,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            charset: " .:-=+*#%@".to_string(),
+            invert: false,
+            ignore_white: false,
+            background: [255, 255, 255],
+            alpha_threshold: 0,
+            ignore_background: None,
+            dither_algorithm: DitherAlgorithm::None,
+            serpentine: false,
+            seed: 0,
+            edge_method: EdgeMethod::None,
+            edge_threshold: 100.0,
+            dog_threshold: 100.0,
+            dog_low_threshold: None,
+            dog_sigma: 0.5,
+            dog_k: 2.0,
+            dog_kernel_size: 3,
+            xdog_tau: 0.0,
+            xdog_phi: 10.0,
+            xdog_epsilon: 0.5,
+            brightness: 0.0,
+            contrast: 0.0,
+            gamma: 1.0,
+            equalization: Equalization::None,
+            clahe_tile_size: 32,
+            clahe_clip_limit: 2.0,
+            auto_levels: 0.0,
+            color_format: ColorFormat::Plain,
+            render_mode: RenderMode::Luminance,
+            glyph_metric: GlyphMetric::Mse,
+            cell_width: 6,
+            cell_height: 10,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ConvertOptions {
+    /// Create options with the default charset and no adjustments
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ConvertOptions {
+        Self::default()
+    }
+
+    /// Characters ordered from darkest to brightest
+    pub fn with_charset(mut self, charset: &str) -> ConvertOptions {
+        self.charset = charset.to_string();
+        self
+    }
+
+    pub fn with_invert(mut self, invert: bool) -> ConvertOptions {
+        self.invert = invert;
+        self
+    }
+
+    /// Emit spaces for pixels that are pure white after invert,
+    /// brightness/contrast and the tone stage
+    pub fn with_ignore_white(mut self, ignore_white: bool) -> ConvertOptions {
+        self.ignore_white = ignore_white;
+        self
+    }
+
+    /// Color transparent pixels are composited over (white by default)
+    pub fn with_background(mut self, r: u8, g: u8, b: u8) -> ConvertOptions {
+        self.background = [r, g, b];
+        self
+    }
+
+    /// Emit spaces for pixels whose alpha is below `threshold`; 0 disables it
+    pub fn with_alpha_threshold(mut self, threshold: u8) -> ConvertOptions {
+        self.alpha_threshold = threshold;
+        self
+    }
+
+    /// Emit spaces for pixels whose composited color is within `tolerance`
+    /// of the background color in every channel (0-255)
+    ///
+    /// Independent of `with_ignore_white`, which looks at the adjusted
+    /// brightness instead of the source color.
+    pub fn with_ignore_background(mut self, tolerance: f64) -> ConvertOptions {
+        self.ignore_background = Some(tolerance);
+        self
+    }
+
+    pub fn with_dither_algorithm(mut self, algorithm: DitherAlgorithm) -> ConvertOptions {
+        self.dither_algorithm = algorithm;
+        self
+    }
+
+    /// Scan alternate rows in opposite directions for error diffusion
+    pub fn with_serpentine(mut self, serpentine: bool) -> ConvertOptions {
+        self.serpentine = serpentine;
+        self
+    }
+
+    /// Seed for noise-based dithering; a given seed always reproduces the
+    /// same output
+    pub fn with_seed(mut self, seed: u32) -> ConvertOptions {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_edge_method(mut self, method: EdgeMethod) -> ConvertOptions {
+        self.edge_method = method;
+        self
+    }
+
+    /// Sobel magnitude threshold (0-255)
+    pub fn with_edge_threshold(mut self, threshold: f64) -> ConvertOptions {
+        self.edge_threshold = threshold;
+        self
+    }
+
+    /// DoG contour magnitude threshold
+    ///
+    /// With hysteresis enabled this is the strong-edge threshold.
+    pub fn with_dog_threshold(mut self, threshold: f64) -> ConvertOptions {
+        self.dog_threshold = threshold;
+        self
+    }
+
+    /// Weak-edge threshold for Canny-style hysteresis
+    ///
+    /// Pixels above this value are kept only when they connect to a pixel
+    /// above `dog_threshold`. Equal thresholds disable hysteresis.
+    pub fn with_dog_low_threshold(mut self, threshold: f64) -> ConvertOptions {
+        self.dog_low_threshold = Some(threshold);
+        self
+    }
+
+    /// Gaussian blur of the DoG: the narrow blur uses `sigma`, the wide one
+    /// `sigma * k`, both sampled over `kernel_size` x `kernel_size` pixels
+    pub fn with_dog_blur(mut self, sigma: f64, k: f64, kernel_size: usize) -> ConvertOptions {
+        self.dog_sigma = sigma;
+        self.dog_k = k;
+        self.dog_kernel_size = kernel_size;
+        self
+    }
+
+    /// Extended DoG (XDoG) sharpening and soft thresholding
+    ///
+    /// `tau` weights the sharpened difference `(1 + tau) * G1 - tau * G2`;
+    /// 0 keeps the plain DoG. Values below `epsilon` (0-1) are darkened with
+    /// a `tanh` ramp whose steepness is `phi`.
+    pub fn with_xdog(mut self, tau: f64, phi: f64, epsilon: f64) -> ConvertOptions {
+        self.xdog_tau = tau;
+        self.xdog_phi = phi;
+        self.xdog_epsilon = epsilon;
+        self
+    }
+
+    /// Brightness offset added after contrast (-255 to 255)
+    pub fn with_brightness(mut self, brightness: f64) -> ConvertOptions {
+        self.brightness = brightness;
+        self
+    }
+
+    /// Contrast adjustment (-255 to 255)
+    pub fn with_contrast(mut self, contrast: f64) -> ConvertOptions {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Gamma correction; values above 1 brighten midtones, 1 disables it
+    pub fn with_gamma(mut self, gamma: f64) -> ConvertOptions {
+        self.gamma = gamma;
+        self
+    }
+
+    pub fn with_equalization(mut self, equalization: Equalization) -> ConvertOptions {
+        self.equalization = equalization;
+        self
+    }
+
+    /// Enable CLAHE with square tiles of `tile_size` pixels
+    ///
+    /// Histogram bins are clipped at `clip_limit` times their average
+    /// height; lower limits amplify noise less, 1 disables equalization.
+    pub fn with_clahe(mut self, tile_size: usize, clip_limit: f64) -> ConvertOptions {
+        self.equalization = Equalization::Clahe;
+        self.clahe_tile_size = tile_size;
+        self.clahe_clip_limit = clip_limit;
+        self
+    }
+
+    /// Stretch levels so that `percentile` percent of the pixels clip to
+    /// black and as many to white (0 to 50, 0 disables it)
+    pub fn with_auto_levels(mut self, percentile: f64) -> ConvertOptions {
+        self.auto_levels = percentile;
+        self
+    }
+
+    pub fn with_color_format(mut self, format: ColorFormat) -> ConvertOptions {
+        self.color_format = format;
+        self
+    }
+
+    pub fn with_render_mode(mut self, mode: RenderMode) -> ConvertOptions {
+        self.render_mode = mode;
+        self
+    }
+
+    /// Glyph matching: similarity measure and source pixels per character
+    ///
+    /// Edge detection and dithering do not apply in glyph matching mode.
+    pub fn with_glyph_match(mut self, metric: GlyphMetric, cell_width: usize, cell_height: usize) -> ConvertOptions {
+        self.glyph_metric = metric;
+        self.cell_width = cell_width;
+        self.cell_height = cell_height;
+        self
+    }
+}
+
+impl ConvertOptions {
+    /// Check the options against an RGBA buffer of `width` x `height` pixels
+    pub(crate) fn validate(&self, data_len: usize, width: usize, height: usize) -> Result<(), String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Image dimensions must be non-zero, got {}x{}", width, height));
+        }
+        let expected_len = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or_else(|| format!("Image dimensions {}x{} are too large", width, height))?;
+        if data_len != expected_len {
+            return Err(format!(
+                "Pixel buffer has {} bytes, expected {} for {}x{} RGBA",
+                data_len, expected_len, width, height
+            ));
+        }
+
+        if self.uses_charset() {
+            let n_levels = self.charset.chars().count();
+            if n_levels == 0 {
+                return Err("Charset must contain at least one character".to_string());
+            }
+            if n_levels < 2 && self.dithering_enabled() {
+                return Err("Dithering needs a charset with at least 2 characters".to_string());
+            }
+        }
+
+        if !(-255.0..=255.0).contains(&self.brightness) {
+            return Err(format!("Brightness must be between -255 and 255, got {}", self.brightness));
+        }
+        if !(-255.0..=255.0).contains(&self.contrast) {
+            return Err(format!("Contrast must be between -255 and 255, got {}", self.contrast));
+        }
+        if !self.ignore_background.is_none_or(is_non_negative) {
+            return Err(format!(
+                "Background tolerance must be a non-negative number, got {}",
+                self.ignore_background.unwrap_or_default()
+            ));
+        }
+        if !is_positive(self.gamma) {
+            return Err(format!("Gamma must be positive, got {}", self.gamma));
+        }
+        if !(0.0..50.0).contains(&self.auto_levels) {
+            return Err(format!(
+                "Auto-levels percentile must be at least 0 and below 50, got {}",
+                self.auto_levels
+            ));
+        }
+        if self.equalization == Equalization::Clahe
+            && (self.clahe_tile_size == 0 || !(self.clahe_clip_limit >= 1.0 && self.clahe_clip_limit.is_finite()))
+        {
+            return Err(format!(
+                "CLAHE needs a non-zero tile size and a clip limit of at least 1, got {} and {}",
+                self.clahe_tile_size, self.clahe_clip_limit
+            ));
+        }
+        if !self.edge_threshold.is_finite()
+            || !self.dog_threshold.is_finite()
+            || !self.dog_low_threshold.unwrap_or(0.0).is_finite()
+        {
+            return Err("Edge thresholds must be finite numbers".to_string());
+        }
+
+        if self.render_mode == RenderMode::GlyphMatch && (self.cell_width == 0 || self.cell_height == 0) {
+            return Err(format!(
+                "Glyph cell size must be non-zero, got {}x{}",
+                self.cell_width, self.cell_height
+            ));
+        }
+
+        if self.uses_contours() {
+            if !is_positive(self.dog_sigma) {
+                return Err(format!("DoG sigma must be positive, got {}", self.dog_sigma));
+            }
+            if !is_positive(self.dog_k) {
+                return Err(format!("DoG sigma ratio must be positive, got {}", self.dog_k));
+            }
+            if self.dog_kernel_size.is_multiple_of(2) || self.dog_kernel_size > MAX_DOG_KERNEL_SIZE {
+                return Err(format!(
+                    "DoG kernel size must be odd and at most {}, got {}",
+                    MAX_DOG_KERNEL_SIZE, self.dog_kernel_size
+                ));
+            }
+            if !is_non_negative(self.xdog_tau)
+                || !is_non_negative(self.xdog_phi)
+                || !(0.0..=1.0).contains(&self.xdog_epsilon)
+            {
+                return Err("XDoG needs tau >= 0, phi >= 0 and epsilon between 0 and 1".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Sub-cell modes draw their own block characters and DoG draws contour
+    // glyphs, every other mode picks from the charset
+    fn uses_charset(&self) -> bool {
+        match self.render_mode {
+            RenderMode::Luminance => self.edge_method != EdgeMethod::Dog,
+            RenderMode::GlyphMatch => true,
+            _ => false,
+        }
+    }
+
+    // Whether the DoG contour pipeline runs for this edge method
+    pub(crate) fn uses_contours(&self) -> bool {
+        self.render_mode == RenderMode::Luminance
+            && matches!(self.edge_method, EdgeMethod::Dog | EdgeMethod::Hybrid)
+    }
+
+    // Sobel output is binary, so dithering is skipped in that mode
+    pub(crate) fn dithering_enabled(&self) -> bool {
+        self.render_mode == RenderMode::Luminance
+            && self.dither_algorithm != DitherAlgorithm::None
+            && self.edge_method != EdgeMethod::Sobel
+    }
+}
+
+fn is_positive(value: f64) -> bool {
+    value > 0.0 && value.is_finite()
+}
+
+fn is_non_negative(value: f64) -> bool {
+    value >= 0.0 && value.is_finite()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_bad_input() {
+        let options = ConvertOptions::new();
+        assert!(options.validate(16, 2, 2).is_ok());
+        assert!(options.validate(15, 2, 2).unwrap_err().contains("expected 16"));
+        assert!(options.validate(0, 0, 2).is_err());
+
+        let empty = ConvertOptions::new().with_charset("");
+        assert!(empty.validate(16, 2, 2).is_err());
+
+        let single = ConvertOptions::new().with_charset("#");
+        assert!(single.validate(16, 2, 2).is_ok());
+        let dithered = single.with_dither_algorithm(DitherAlgorithm::FloydSteinberg);
+        assert!(dithered.validate(16, 2, 2).is_err());
+
+        let dog = ConvertOptions::new().with_edge_method(EdgeMethod::Dog);
+        assert!(dog.clone().with_dog_blur(1.0, 1.6, 7).validate(16, 2, 2).is_ok());
+        assert!(dog.clone().with_dog_blur(1.0, 1.6, 4).validate(16, 2, 2).is_err());
+        assert!(dog.with_xdog(20.0, 10.0, 1.5).validate(16, 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_parse_legacy_names() {
+        assert_eq!(EdgeMethod::from_str("dog"), Ok(EdgeMethod::Dog));
+        assert_eq!(DitherAlgorithm::from_str("floyd"), Ok(DitherAlgorithm::FloydSteinberg));
+        assert!(DitherAlgorithm::from_str("blue").is_err());
+    }
+}
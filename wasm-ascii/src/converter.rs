@@ -0,0 +1,215 @@
+use wasm_bindgen::prelude::*;
+
+use crate::background::composite_over;
+use crate::convert_options::ConvertOptions;
+use crate::{convert_to_grid, render_grid, CellGrid, FrameBuffers};
+
+/// Converter for a stream of same-sized frames, e.g. a live camera
+///
+/// Keeps its compositing, grayscale, dithering, contour and color buffers
+/// between frames, so steady-state conversion only allocates the returned
+/// text. With smoothing
+/// enabled, a cell only changes character once its brightness has moved
+/// far enough from where it last changed, which suppresses flicker.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct AsciiConverter {
+    width: usize,
+    height: usize,
+    options: ConvertOptions,
+    smoothing: f64,
+    buffers: FrameBuffers,
+    // Per-cell character shown last and the cell brightness when it changed
+    shown: Vec<char>,
+    anchors: Vec<f64>,
+    cell_gray: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl AsciiConverter {
+    /// Create a converter for `width` x `height` RGBA frames
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize, options: &ConvertOptions) -> Result<AsciiConverter, String> {
+        let expected_len = width.saturating_mul(height).saturating_mul(4);
+        options.validate(expected_len, width, height)?;
+        Ok(AsciiConverter {
+            width,
+            height,
+            options: options.clone(),
+            smoothing: 0.0,
+            buffers: FrameBuffers::default(),
+            shown: Vec::new(),
+            anchors: Vec::new(),
+            cell_gray: Vec::new(),
+        })
+    }
+
+    /// Temporal smoothing threshold in brightness levels (0-255)
+    ///
+    /// A cell keeps its character until its average brightness differs by
+    /// at least `threshold` from the brightness it had when the character
+    /// last changed. 0 disables smoothing.
+    pub fn with_smoothing(mut self, threshold: f64) -> AsciiConverter {
+        self.smoothing = threshold;
+        self
+    }
+
+    /// Switch to new options, e.g. when a UI control changes
+    ///
+    /// Smoothing restarts from the next frame.
+    pub fn set_options(&mut self, options: &ConvertOptions) -> Result<(), String> {
+        let expected_len = self.width.saturating_mul(self.height).saturating_mul(4);
+        options.validate(expected_len, self.width, self.height)?;
+        self.options = options.clone();
+        self.reset();
+        Ok(())
+    }
+
+    /// Forget the previous frames so the next one is shown unsmoothed
+    pub fn reset(&mut self) {
+        self.shown.clear();
+        self.anchors.clear();
+    }
+
+    /// Convert one RGBA frame of the size given to the constructor
+    pub fn convert_frame(&mut self, data: &[u8]) -> Result<String, String> {
+        let expected_len = self.width * self.height * 4;
+        if data.len() != expected_len {
+            return Err(format!(
+                "Pixel buffer has {} bytes, expected {} for {}x{} RGBA",
+                data.len(),
+                expected_len,
+                self.width,
+                self.height
+            ));
+        }
+
+        composite_over(data, self.options.background, &mut self.buffers.composited);
+        let grid = convert_to_grid(self.width, self.height, &self.options, &mut self.buffers);
+        if self.smoothing > 0.0 {
+            self.smooth(&grid);
+        }
+        Ok(render_grid(self.width, self.height, &grid, &self.options, &mut self.buffers))
+    }
+}
+
+impl AsciiConverter {
+    // Hold each cell's previous character while its brightness stays within
+    // the smoothing threshold of the brightness it changed at
+    fn smooth(&mut self, grid: &CellGrid) {
+        cell_means(&self.buffers.gray, self.width, self.height, grid, &mut self.cell_gray);
+        let cells = &mut self.buffers.cells;
+
+        if self.shown.len() != cells.len() {
+            self.shown.clone_from(cells);
+            self.anchors.clone_from(&self.cell_gray);
+            return;
+        }
+
+        for ((cell, shown), (anchor, &gray)) in cells
+            .iter_mut()
+            .zip(self.shown.iter_mut())
+            .zip(self.anchors.iter_mut().zip(&self.cell_gray))
+        {
+            if *cell == *shown {
+                continue;
+            }
+            if (gray - *anchor).abs() < self.smoothing {
+                *cell = *shown;
+            } else {
+                *shown = *cell;
+                *anchor = gray;
+            }
+        }
+    }
+}
+
+// Average brightness of the pixels under each cell
+fn cell_means(gray: &[f64], width: usize, height: usize, grid: &CellGrid, out: &mut Vec<f64>) {
+    out.clear();
+    if grid.cell_width == 1 && grid.cell_height == 1 {
+        out.extend_from_slice(gray);
+        return;
+    }
+    for row in 0..grid.rows {
+        let y0 = row * grid.cell_height;
+        let y1 = (y0 + grid.cell_height).min(height);
+        for col in 0..grid.cols {
+            let x0 = col * grid.cell_width;
+            let x1 = (x0 + grid.cell_width).min(width);
+            let sum: f64 = (y0..y1).map(|y| gray[y * width + x0..y * width + x1].iter().sum::<f64>()).sum();
+            out.push(sum / ((y1 - y0) * (x1 - x0)) as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_with_options;
+
+    fn gray_pixel(v: u8) -> [u8; 4] {
+        [v, v, v, 255]
+    }
+
+    #[test]
+    fn test_frames_match_one_shot_conversion() {
+        let options = ConvertOptions::new().with_edge_method(crate::EdgeMethod::Hybrid);
+        let mut converter = AsciiConverter::new(8, 4, &options).unwrap();
+        let frames: Vec<Vec<u8>> = (0..3usize)
+            .map(|f| (0..32usize).flat_map(|i| [(i * 7 + f * 40) as u8, 0, 0, 255]).collect())
+            .collect();
+        for frame in &frames {
+            assert_eq!(converter.convert_frame(frame).unwrap(), convert_with_options(frame, 8, 4, &options).unwrap());
+        }
+        assert!(converter.convert_frame(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_steady_state_reuses_buffers() {
+        let options = ConvertOptions::new()
+            .with_render_mode(crate::RenderMode::HalfBlock)
+            .with_color_format(crate::ColorFormat::AnsiTrueColor)
+            .with_alpha_threshold(64);
+        let mut converter = AsciiConverter::new(6, 4, &options).unwrap();
+        let frame = |shift: usize| -> Vec<u8> {
+            (0..24usize).flat_map(|i| [(i * 10 + shift) as u8, 0, 0, (i * 11) as u8]).collect()
+        };
+        converter.convert_frame(&frame(0)).unwrap();
+        let buffers = &converter.buffers;
+        let before = [
+            buffers.composited.as_ptr() as usize,
+            buffers.blank.as_ptr() as usize,
+            buffers.cells.as_ptr() as usize,
+            buffers.colors.as_ptr() as usize,
+            buffers.backgrounds.as_ptr() as usize,
+        ];
+
+        converter.convert_frame(&frame(5)).unwrap();
+        let buffers = &converter.buffers;
+        let after = [
+            buffers.composited.as_ptr() as usize,
+            buffers.blank.as_ptr() as usize,
+            buffers.cells.as_ptr() as usize,
+            buffers.colors.as_ptr() as usize,
+            buffers.backgrounds.as_ptr() as usize,
+        ];
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn test_smoothing_holds_characters_until_threshold() {
+        let options = ConvertOptions::new().with_charset(" #");
+        let mut converter = AsciiConverter::new(1, 1, &options).unwrap().with_smoothing(30.0);
+
+        assert_eq!(converter.convert_frame(&gray_pixel(120)).unwrap(), " \n");
+        // Crosses the 127.5 cut but stays within 30 levels of 120
+        assert_eq!(converter.convert_frame(&gray_pixel(135)).unwrap(), " \n");
+        assert_eq!(converter.convert_frame(&gray_pixel(155)).unwrap(), "#\n");
+        // Back below the cut, but only 29 levels from where it changed
+        assert_eq!(converter.convert_frame(&gray_pixel(126)).unwrap(), "#\n");
+
+        converter.reset();
+        assert_eq!(converter.convert_frame(&gray_pixel(126)).unwrap(), " \n");
+    }
+}
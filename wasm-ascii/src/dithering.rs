@@ -0,0 +1,275 @@
+use crate::blue_noise::{cached_blue_noise_ranks, BLUE_NOISE_SIZE};
+use crate::clamp;
+use crate::convert_options::DitherAlgorithm;
+use crate::rng::Rng;
+
+// An error-diffusion kernel: each tap sends `weight / divisor` of the
+// quantization error to the pixel at (`dx`, `dy`) from the current one
+struct DiffusionKernel {
+    divisor: f64,
+    taps: &'static [(isize, usize, f64)],
+}
+
+const FLOYD_STEINBERG: DiffusionKernel = DiffusionKernel {
+    divisor: 16.0,
+    taps: &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)],
+};
+
+// Atkinson only propagates 6/8 of the error, which keeps highlights open
+const ATKINSON: DiffusionKernel = DiffusionKernel {
+    divisor: 8.0,
+    taps: &[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)],
+};
+
+const JARVIS_JUDICE_NINKE: DiffusionKernel = DiffusionKernel {
+    divisor: 48.0,
+    taps: &[
+        (1, 0, 7.0), (2, 0, 5.0),
+        (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+        (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+    ],
+};
+
+const STUCKI: DiffusionKernel = DiffusionKernel {
+    divisor: 42.0,
+    taps: &[
+        (1, 0, 8.0), (2, 0, 4.0),
+        (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+        (-2, 2, 1.0), (-1, 2, 2.0), (0, 2, 4.0), (1, 2, 2.0), (2, 2, 1.0),
+    ],
+};
+
+const BURKES: DiffusionKernel = DiffusionKernel {
+    divisor: 32.0,
+    taps: &[
+        (1, 0, 8.0), (2, 0, 4.0),
+        (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+    ],
+};
+
+const SIERRA: DiffusionKernel = DiffusionKernel {
+    divisor: 32.0,
+    taps: &[
+        (1, 0, 5.0), (2, 0, 3.0),
+        (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 5.0), (1, 1, 4.0), (2, 1, 2.0),
+        (-1, 2, 2.0), (0, 2, 3.0), (1, 2, 2.0),
+    ],
+};
+
+const SIERRA_TWO_ROW: DiffusionKernel = DiffusionKernel {
+    divisor: 16.0,
+    taps: &[
+        (1, 0, 4.0), (2, 0, 3.0),
+        (-2, 1, 1.0), (-1, 1, 2.0), (0, 1, 3.0), (1, 1, 2.0), (2, 1, 1.0),
+    ],
+};
+
+const SIERRA_LITE: DiffusionKernel = DiffusionKernel {
+    divisor: 4.0,
+    taps: &[(1, 0, 2.0), (-1, 1, 1.0), (0, 1, 1.0)],
+};
+
+/// Quantize `gray` in place to `n_levels` evenly spaced values with `algorithm`
+///
+/// With `serpentine`, error-diffusion kernels scan odd rows right to left
+/// and mirror horizontally, which breaks up the directional "worm"
+/// artifacts of a plain raster scan. Other algorithms ignore it.
+///
+/// `seed` drives the noise-based algorithms; the same seed always gives the
+/// same output.
+pub(crate) fn apply_dithering(
+    gray: &mut [f64],
+    width: usize,
+    height: usize,
+    n_levels: usize,
+    algorithm: DitherAlgorithm,
+    serpentine: bool,
+    seed: u32,
+) {
+    let kernel = match algorithm {
+        DitherAlgorithm::None => return,
+        DitherAlgorithm::Noise => return apply_noise_dithering(gray, n_levels, seed),
+        DitherAlgorithm::BlueNoise => {
+            let ranks = cached_blue_noise_ranks(seed);
+            return apply_threshold_map(gray, width, height, n_levels, &ranks, BLUE_NOISE_SIZE);
+        }
+        DitherAlgorithm::Ordered => return apply_threshold_map(gray, width, height, n_levels, &bayer_matrix(4), 4),
+        DitherAlgorithm::Ordered8x8 => return apply_threshold_map(gray, width, height, n_levels, &bayer_matrix(8), 8),
+        DitherAlgorithm::Ordered16x16 => {
+            return apply_threshold_map(gray, width, height, n_levels, &bayer_matrix(16), 16)
+        }
+        DitherAlgorithm::FloydSteinberg => &FLOYD_STEINBERG,
+        DitherAlgorithm::Atkinson => &ATKINSON,
+        DitherAlgorithm::JarvisJudiceNinke => &JARVIS_JUDICE_NINKE,
+        DitherAlgorithm::Stucki => &STUCKI,
+        DitherAlgorithm::Burkes => &BURKES,
+        DitherAlgorithm::Sierra => &SIERRA,
+        DitherAlgorithm::SierraTwoRow => &SIERRA_TWO_ROW,
+        DitherAlgorithm::SierraLite => &SIERRA_LITE,
+    };
+    apply_error_diffusion(gray, width, height, n_levels, kernel, serpentine)
+}
+
+// Error diffusion with any kernel from the table above
+fn apply_error_diffusion(
+    result: &mut [f64],
+    width: usize,
+    height: usize,
+    n_levels: usize,
+    kernel: &DiffusionKernel,
+    serpentine: bool,
+) {
+    let steps = (n_levels - 1) as f64;
+
+    for y in 0..height {
+        let reverse = serpentine && y % 2 == 1;
+        for i in 0..width {
+            let x = if reverse { width - 1 - i } else { i };
+            let idx = y * width + x;
+            let computed_level = ((result[idx] / 255.0) * steps).round();
+            let new_pixel = (computed_level / steps) * 255.0;
+            let error = result[idx] - new_pixel;
+
+            result[idx] = new_pixel;
+
+            for &(dx, dy, weight) in kernel.taps {
+                let dx = if reverse { -dx } else { dx };
+                let (Some(nx), ny) = (x.checked_add_signed(dx), y + dy) else {
+                    continue;
+                };
+                if nx < width && ny < height {
+                    let n = ny * width + nx;
+                    result[n] = clamp(result[n] + error * weight / kernel.divisor, 0.0, 255.0);
+                }
+            }
+        }
+    }
+}
+
+// White-noise dithering: uniform noise of one quantization step, drawn
+// from a PRNG seeded with `seed`
+fn apply_noise_dithering(gray: &mut [f64], n_levels: usize, seed: u32) {
+    let steps = (n_levels - 1) as f64;
+    let step = 255.0 / steps;
+    let mut rng = Rng::new(seed as u64);
+    for pixel in gray.iter_mut() {
+        let noise = (rng.next_f64() - 0.5) * step;
+        let noisy_value = clamp(*pixel + noise, 0.0, 255.0);
+        *pixel = ((noisy_value / 255.0) * steps).round() * step;
+    }
+}
+
+// Bayer index matrix of side `size` (a power of two), row-major
+//
+// Built recursively: each step tiles 4 * M with offsets 0, 2, 3, 1.
+fn bayer_matrix(size: usize) -> Vec<usize> {
+    let mut matrix = vec![0];
+    let mut n = 1;
+    while n < size {
+        let mut next = vec![0; 4 * n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let v = 4 * matrix[y * n + x];
+                next[y * 2 * n + x] = v;
+                next[y * 2 * n + x + n] = v + 2;
+                next[(y + n) * 2 * n + x] = v + 3;
+                next[(y + n) * 2 * n + x + n] = v + 1;
+            }
+        }
+        matrix = next;
+        n *= 2;
+    }
+    matrix
+}
+
+// Ordered dithering with a tiled threshold map of side `size` whose
+// entries are ranks in `0..size * size` (Bayer or blue noise)
+fn apply_threshold_map(
+    gray: &mut [f64],
+    width: usize,
+    height: usize,
+    n_levels: usize,
+    map: &[usize],
+    size: usize,
+) {
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+            let p = gray[idx] / 255.0;
+            let t = (map[(y % size) * size + x % size] as f64 + 0.5) / (size * size) as f64;
+            let value_with_dither = (p + t - 0.5).clamp(0.0, 1.0);
+            let computed_level = ((value_with_dither * n_levels as f64) as usize).min(n_levels - 1);
+            gray[idx] = (computed_level as f64 / (n_levels - 1) as f64) * 255.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bayer_matrix_matches_classic_4x4() {
+        assert_eq!(
+            bayer_matrix(4),
+            vec![0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]
+        );
+        let mut values = bayer_matrix(16);
+        values.sort_unstable();
+        assert_eq!(values, (0..256).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_kernels_preserve_mean_tone() {
+        let (width, height) = (32, 32);
+        let gray = vec![100.0; width * height];
+        for algorithm in [
+            DitherAlgorithm::FloydSteinberg,
+            DitherAlgorithm::JarvisJudiceNinke,
+            DitherAlgorithm::Stucki,
+            DitherAlgorithm::Burkes,
+            DitherAlgorithm::Sierra,
+            DitherAlgorithm::SierraTwoRow,
+            DitherAlgorithm::SierraLite,
+        ] {
+            for serpentine in [false, true] {
+                let mut out = gray.clone();
+                apply_dithering(&mut out, width, height, 2, algorithm, serpentine, 0);
+                assert!(out.iter().all(|&v| v == 0.0 || v == 255.0));
+                let mean = out.iter().sum::<f64>() / out.len() as f64;
+                assert!((mean - 100.0).abs() < 10.0, "{:?} mean {}", algorithm, mean);
+            }
+        }
+    }
+
+    #[test]
+    fn test_serpentine_reverses_odd_rows() {
+        // The second row's error flows right in a raster scan and left in a
+        // serpentine one, so a different pixel crosses the threshold
+        let mut raster = vec![0.0, 0.0, 0.0, 120.0, 120.0, 0.0];
+        let mut serpentine = raster.clone();
+        apply_dithering(&mut raster, 3, 2, 2, DitherAlgorithm::FloydSteinberg, false, 0);
+        apply_dithering(&mut serpentine, 3, 2, 2, DitherAlgorithm::FloydSteinberg, true, 0);
+        assert_eq!(raster[3..], [0.0, 255.0, 0.0]);
+        assert_eq!(serpentine[3..], [255.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_noise_is_reproducible_per_seed() {
+        let (width, height) = (64, 64);
+        let gray: Vec<f64> = (0..width * height).map(|i| (i % 256) as f64).collect();
+        for algorithm in [DitherAlgorithm::Noise, DitherAlgorithm::BlueNoise] {
+            let run = |seed| {
+                let mut out = gray.clone();
+                apply_dithering(&mut out, width, height, 2, algorithm, false, seed);
+                out
+            };
+            let out = run(3);
+            assert_eq!(out, run(3));
+            assert_ne!(out, run(4));
+            let mean = out.iter().sum::<f64>() / out.len() as f64;
+            let expected = gray.iter().sum::<f64>() / gray.len() as f64;
+            assert!((mean - expected).abs() < 8.0, "{:?} mean {}", algorithm, mean);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use wasm_bindgen::prelude::*;
+use std::f64::consts::TAU;
+
+use crate::frame_size::check_frame_size;
+use crate::noise::Perlin;
+use crate::rng::Rng;
+
+// Default shades from empty to brightest, as in the image converter
+const DEFAULT_SHADES: &str = " .:-=+*#%@";
+// Default glyphs for matrix rain
+const DEFAULT_RAIN_GLYPHS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ$+-*/=%\"'#&_(),.;:?!\\|{}<>[]^~";
+
+/// Size, seed and charset shared by the procedural effects
+///
+/// One options value can drive every effect in a render loop; an effect
+/// fails only when the frame size is empty or over the frame size limit.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct EffectOptions {
+    width: usize,
+    height: usize,
+    seed: u32,
+    charset: Option<Vec<char>>,
+}
+
+impl Default for EffectOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            height: 24,
+            seed: 0,
+            charset: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl EffectOptions {
+    /// 80x24 characters, seed 0, each effect's default charset
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> EffectOptions {
+        Self::default()
+    }
+
+    /// Frame size in characters
+    pub fn with_size(mut self, width: usize, height: usize) -> EffectOptions {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Seed for everything random in the effect; the same seed and time
+    /// always give the same frame
+    pub fn with_seed(mut self, seed: u32) -> EffectOptions {
+        self.seed = seed;
+        self
+    }
+
+    /// Shades from darkest to brightest, or for matrix rain the glyphs the
+    /// rain is made of
+    ///
+    /// Line breaks are dropped; an empty charset keeps each effect's default.
+    pub fn with_charset(mut self, charset: &str) -> EffectOptions {
+        let chars: Vec<char> = charset.chars().filter(|&c| c != '\n' && c != '\r').collect();
+        self.charset = (!chars.is_empty()).then_some(chars);
+        self
+    }
+}
+
+impl EffectOptions {
+    // Charset to draw with, after checking the frame size
+    fn prepare(&self, default: &str) -> Result<Vec<char>, String> {
+        check_frame_size("Effect size", "cells", self.width, self.height)?;
+        Ok(self.charset.clone().unwrap_or_else(|| default.chars().collect()))
+    }
+
+    // Draw every cell with `cell(x, y)`, one line per row
+    fn draw(&self, mut cell: impl FnMut(usize, usize) -> char) -> String {
+        let mut result = String::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                result.push(cell(x, y));
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
+
+// Shade for brightness `v` in [0, 1]
+fn shade(shades: &[char], v: f64) -> char {
+    let index = (v.clamp(0.0, 1.0) * shades.len() as f64) as usize;
+    shades[index.min(shades.len() - 1)]
+}
+
+// Uniform value in [0, 1) fixed by the seed and two coordinates
+fn hashed(seed: u32, a: u64, b: u64) -> f64 {
+    let key = (seed as u64) ^ a.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ b.wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    Rng::new(key).next_f64()
+}
+
+/// Plasma: overlapping sine waves drifting across the frame
+///
+/// # Arguments
+/// * `time` - Animation time in seconds
+/// * `options` - Size, seed and shades
+///
+/// # Returns
+/// One frame, `height` lines of `width` characters, or an error if the
+/// size is out of range
+#[wasm_bindgen]
+pub fn generate_plasma_frame(time: f64, options: &EffectOptions) -> Result<String, String> {
+    let shades = options.prepare(DEFAULT_SHADES)?;
+    let mut rng = Rng::new(options.seed as u64);
+    let phases: Vec<f64> = (0..4).map(|_| rng.next_f64() * TAU).collect();
+    let scale = 0.08 + rng.next_f64() * 0.08;
+
+    // Center of the radial wave circles the middle of the frame
+    let center_x = options.width as f64 * (0.5 + 0.3 * (time * 0.3 + phases[0]).sin());
+    let center_y = options.height as f64 * (0.5 + 0.3 * (time * 0.4 + phases[1]).cos());
+
+    Ok(options.draw(|x, y| {
+        // Cells are about twice as tall as wide
+        let nx = x as f64 * scale;
+        let ny = y as f64 * scale * 2.0;
+        let dx = (x as f64 - center_x) * scale;
+        let dy = (y as f64 - center_y) * scale * 2.0;
+        let v = (nx + time + phases[0]).sin()
+            + (ny - time * 0.7 + phases[1]).sin()
+            + ((nx + ny) * 0.7 + time * 0.5 + phases[2]).sin()
+            + ((dx * dx + dy * dy).sqrt() - time * 1.3 + phases[3]).sin();
+        shade(&shades, (v / 4.0 + 1.0) / 2.0)
+    }))
+}
+
+/// Fire: turbulent flames rising from the bottom edge
+///
+/// # Arguments
+/// * `time` - Animation time in seconds
+/// * `options` - Size, seed and shades
+///
+/// # Returns
+/// One frame, `height` lines of `width` characters, or an error if the
+/// size is out of range
+#[wasm_bindgen]
+pub fn generate_fire_frame(time: f64, options: &EffectOptions) -> Result<String, String> {
+    let shades = options.prepare(DEFAULT_SHADES)?;
+    let noise = Perlin::new(options.seed);
+    let height = options.height as f64;
+
+    Ok(options.draw(|x, y| {
+        // 1 at the bottom row, falling to 0 at the top
+        let base = (y + 1) as f64 / height;
+        // Sampling further down the noise as time passes moves flames up
+        let turbulence = noise.fbm(x as f64 * 0.12, y as f64 * 0.25 + time * 2.5, 4);
+        let heat = base.powf(1.5) * (0.7 + 0.9 * turbulence) + 0.25 * base.powi(4);
+        shade(&shades, heat)
+    }))
+}
+
+/// Matrix rain: columns of glyphs falling at their own speeds
+///
+/// Each column's drops leave a trail that dissolves toward its tail, and
+/// glyphs flicker as they fall.
+///
+/// # Arguments
+/// * `time` - Animation time in seconds
+/// * `options` - Size, seed and the glyphs to rain
+///
+/// # Returns
+/// One frame, `height` lines of `width` characters, or an error if the
+/// size is out of range
+#[wasm_bindgen]
+pub fn generate_matrix_frame(time: f64, options: &EffectOptions) -> Result<String, String> {
+    let glyphs = options.prepare(DEFAULT_RAIN_GLYPHS)?;
+    let seed = options.seed;
+    let height = options.height as f64;
+
+    Ok(options.draw(|x, y| {
+        let column = x as u64;
+        // Rows per second, trail length and gap before the next drop
+        let speed = 6.0 + hashed(seed, column, 1) * 12.0;
+        let trail = 4.0 + hashed(seed, column, 2) * height * 0.6;
+        let cycle = height + trail + hashed(seed, column, 3) * height;
+        let travelled = time * speed + hashed(seed, column, 4) * cycle;
+        let drop = (travelled / cycle).floor();
+        let distance = travelled - drop * cycle - y as f64;
+
+        if !(0.0..trail).contains(&distance) {
+            return ' ';
+        }
+        // Tail cells drop out more often the further they are from the head
+        let cell = (column << 32) | y as u64;
+        if distance >= 1.0 && hashed(seed, cell, drop as u64) < distance / trail * 0.7 {
+            return ' ';
+        }
+        let flicker = (time * 8.0 + hashed(seed, cell, 5) * 8.0).floor() as u64;
+        let index = (hashed(seed, cell, flicker.wrapping_add(6)) * glyphs.len() as f64) as usize;
+        glyphs[index.min(glyphs.len() - 1)]
+    }))
+}
+
+/// Starfield: stars flying toward the viewer, brightening as they near
+///
+/// # Arguments
+/// * `time` - Animation time in seconds
+/// * `options` - Size, seed and shades
+///
+/// # Returns
+/// One frame, `height` lines of `width` characters, or an error if the
+/// size is out of range
+#[wasm_bindgen]
+pub fn generate_starfield_frame(time: f64, options: &EffectOptions) -> Result<String, String> {
+    let shades = options.prepare(DEFAULT_SHADES)?;
+    let (width, height) = (options.width, options.height);
+    let mut brightness = vec![0.0f64; width * height];
+
+    let star_count = (width * height / 12).max(1);
+    let mut rng = Rng::new(options.seed as u64);
+    for _ in 0..star_count {
+        let sx = rng.next_f64() * 2.0 - 1.0;
+        let sy = rng.next_f64() * 2.0 - 1.0;
+        let start = rng.next_f64();
+        // Each star takes 4 seconds from the far plane to the viewer
+        let z = 1.0 - (start + time * 0.25).rem_euclid(1.0);
+        if z < 0.02 {
+            continue;
+        }
+        let px = width as f64 * (0.5 + sx * 0.25 / z);
+        let py = height as f64 * (0.5 + sy * 0.25 / z);
+        if px < 0.0 || py < 0.0 || px >= width as f64 || py >= height as f64 {
+            continue;
+        }
+        let cell = &mut brightness[py as usize * width + px as usize];
+        *cell = cell.max(1.0 - z);
+    }
+
+    Ok(options.draw(|x, y| shade(&shades, brightness[y * width + x])))
+}
+
+/// Landscape: two Perlin-noise mountain ridges scrolling with parallax
+///
+/// # Arguments
+/// * `time` - Animation time in seconds
+/// * `options` - Size, seed and shades
+///
+/// # Returns
+/// One frame, `height` lines of `width` characters, or an error if the
+/// size is out of range
+#[wasm_bindgen]
+pub fn generate_landscape_frame(time: f64, options: &EffectOptions) -> Result<String, String> {
+    let shades = options.prepare(DEFAULT_SHADES)?;
+    let noise = Perlin::new(options.seed);
+    let height = options.height as f64;
+
+    // Top row of each ridge per column; the far ridge scrolls slower
+    let ridge = |x: usize, scale: f64, speed: f64, row: f64, base: f64| {
+        let v = noise.fbm(x as f64 * scale + time * speed, row, 4);
+        height * (base - 0.8 * v)
+    };
+    let far: Vec<f64> = (0..options.width).map(|x| ridge(x, 0.05, 1.5, 11.5, 0.45)).collect();
+    let near: Vec<f64> = (0..options.width).map(|x| ridge(x, 0.09, 4.0, 23.5, 0.7)).collect();
+
+    Ok(options.draw(|x, y| {
+        let y = y as f64;
+        if y >= near[x] {
+            // Lit ridge line fading with depth
+            shade(&shades, 1.0 - 0.5 * (y - near[x]) / (height - near[x]).max(1.0))
+        } else if y >= far[x] {
+            shade(&shades, 0.35)
+        } else {
+            shade(&shades, 0.0)
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Effect = fn(f64, &EffectOptions) -> Result<String, String>;
+
+    const EFFECTS: [Effect; 5] = [
+        generate_plasma_frame,
+        generate_fire_frame,
+        generate_matrix_frame,
+        generate_starfield_frame,
+        generate_landscape_frame,
+    ];
+
+    #[test]
+    fn test_effects_are_sized_seeded_and_animated() {
+        let options = EffectOptions::new().with_size(30, 12).with_seed(5);
+        for (i, effect) in EFFECTS.iter().enumerate() {
+            let frame = effect(1.5, &options).unwrap();
+            let lines: Vec<&str> = frame.lines().collect();
+            assert_eq!(lines.len(), 12, "effect {}", i);
+            assert!(lines.iter().all(|line| line.chars().count() == 30), "effect {}", i);
+            assert!(frame.chars().any(|c| c != ' ' && c != '\n'), "effect {} is blank", i);
+
+            assert_eq!(frame, effect(1.5, &options).unwrap(), "effect {}", i);
+            assert_ne!(frame, effect(2.5, &options).unwrap(), "effect {} is static", i);
+            assert_ne!(frame, effect(1.5, &options.clone().with_seed(6)).unwrap(), "effect {}", i);
+        }
+    }
+
+    #[test]
+    fn test_effects_draw_with_the_charset_and_reject_bad_sizes() {
+        let options = EffectOptions::new().with_size(20, 10).with_charset(" o\n");
+        for effect in EFFECTS {
+            let frame = effect(0.0, &options).unwrap();
+            assert!(frame.chars().all(|c| " o\n".contains(c)));
+            assert_eq!(frame.lines().count(), 10);
+
+            assert!(effect(0.0, &options.clone().with_size(0, 10)).is_err());
+            assert!(effect(0.0, &options.clone().with_size(8192, 8192)).is_err());
+        }
+
+        let defaults = options.with_charset("");
+        assert_eq!(generate_fire_frame(0.0, &defaults), generate_fire_frame(0.0, &EffectOptions::new().with_size(20, 10)));
+    }
+}
@@ -0,0 +1,411 @@
+use wasm_bindgen::prelude::*;
+use std::collections::HashMap;
+
+use crate::text_in_center::center_in_grid;
+
+// Smushing rule bits of the header's layout fields
+const SMUSH_EQUAL: u32 = 1;
+const SMUSH_LOWLINE: u32 = 2;
+const SMUSH_HIERARCHY: u32 = 4;
+const SMUSH_PAIR: u32 = 8;
+const SMUSH_BIG_X: u32 = 16;
+const SMUSH_HARDBLANK: u32 = 32;
+const SMUSH_RULES: u32 = 63;
+const LAYOUT_KERNING: u32 = 64;
+const LAYOUT_SMUSHING: u32 = 128;
+
+// Code points of the optional Deutsch characters following the required
+// ASCII set, in file order
+const DEUTSCH_CHARS: [u32; 7] = [196, 214, 220, 228, 246, 252, 223];
+
+/// How adjacent characters are joined horizontally
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layout {
+    /// Every character keeps its full width
+    FullWidth,
+    /// Characters move together until they touch
+    Kerning,
+    /// Characters overlap by one column where the rules allow it; no rule
+    /// bits means universal smushing
+    Smushing(u32),
+}
+
+/// Font parsed from FIGlet `.flf` data
+#[derive(Debug, Clone)]
+pub(crate) struct FigletFont {
+    hardblank: char,
+    height: usize,
+    layout: Layout,
+    glyphs: HashMap<char, Vec<Vec<char>>>,
+}
+
+impl FigletFont {
+    /// Parse `.flf` font data
+    ///
+    /// Reads the header, skips the comment, then loads the required ASCII
+    /// characters, the Deutsch characters and any code-tagged characters.
+    /// Fonts that end early simply lack the remaining characters.
+    pub(crate) fn parse(data: &[u8]) -> Result<FigletFont, String> {
+        if data.starts_with(b"PK") {
+            return Err("Compressed FIGlet fonts are not supported; unzip the .flf first".to_string());
+        }
+        let text = String::from_utf8_lossy(data);
+        let mut lines = text.lines();
+
+        let header = lines.next().ok_or_else(|| "FIGlet font is empty".to_string())?;
+        let signature = header
+            .strip_prefix("flf2a")
+            .ok_or_else(|| "Not a FIGlet font: missing flf2a signature".to_string())?;
+        let hardblank = signature
+            .chars()
+            .next()
+            .ok_or_else(|| "FIGlet header has no hardblank character".to_string())?;
+        let fields: Vec<i64> = signature[hardblank.len_utf8()..]
+            .split_whitespace()
+            .map(|field| field.parse::<i64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid FIGlet header: {}", e))?;
+        if fields.len() < 5 {
+            return Err(format!("FIGlet header has {} fields, expected at least 5", fields.len()));
+        }
+
+        let height = usize::try_from(fields[0])
+            .ok()
+            .filter(|&h| h > 0)
+            .ok_or_else(|| format!("Invalid FIGlet character height: {}", fields[0]))?;
+        let comment_lines = usize::try_from(fields[4])
+            .map_err(|_| format!("Invalid FIGlet comment line count: {}", fields[4]))?;
+        let layout = match fields.get(6) {
+            Some(&full_layout) => layout_from_full(full_layout),
+            None => layout_from_old(fields[3]),
+        };
+
+        for _ in 0..comment_lines {
+            lines
+                .next()
+                .ok_or_else(|| "FIGlet font ends inside its comment".to_string())?;
+        }
+
+        let mut glyphs = HashMap::new();
+        for code in (32..=126).chain(DEUTSCH_CHARS) {
+            match read_glyph(&mut lines, height)? {
+                Some(glyph) => {
+                    if let Some(c) = char::from_u32(code) {
+                        glyphs.insert(c, glyph);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        // Code-tagged characters: a line starting with the code, then the rows
+        while let Some(tag) = lines.next() {
+            let Some(code) = tag.split_whitespace().next() else {
+                continue;
+            };
+            let code = parse_code(code).ok_or_else(|| format!("Invalid FIGlet character code: {}", code))?;
+            let glyph = read_glyph(&mut lines, height)?
+                .ok_or_else(|| format!("FIGlet font ends before character {}", code))?;
+            // Negative codes are only meaningful to translation tables
+            if let Some(c) = u32::try_from(code).ok().and_then(char::from_u32) {
+                glyphs.insert(c, glyph);
+            }
+        }
+
+        Ok(FigletFont { hardblank, height, layout, glyphs })
+    }
+
+    /// Render one line of text, without joining characters the font lacks
+    pub(crate) fn render_line(&self, text: &str) -> Vec<String> {
+        let mut output: Vec<Vec<char>> = vec![Vec::new(); self.height];
+        let mut previous_width = 0;
+
+        for c in text.chars() {
+            let Some(glyph) = self.glyphs.get(&c) else {
+                continue;
+            };
+            let width = glyph[0].len();
+            let overlap = self.overlap(&output, glyph, previous_width, width);
+            let line_len = output[0].len();
+
+            for (line, row) in output.iter_mut().zip(glyph) {
+                // Columns pushed past the left edge are blank, so drop them
+                for (k, &right) in row.iter().enumerate().take(overlap) {
+                    if let Some(column) = (line_len + k).checked_sub(overlap) {
+                        line[column] = self
+                            .smush(line[column], right, previous_width, width)
+                            .unwrap_or(right);
+                    }
+                }
+                line.extend_from_slice(&row[overlap..]);
+            }
+            previous_width = width;
+        }
+
+        output
+            .into_iter()
+            .map(|line| line.into_iter().map(|c| if c == self.hardblank { ' ' } else { c }).collect())
+            .collect()
+    }
+
+    // Columns the next glyph can slide into the output: the smallest over all
+    // rows, plus one where the touching pair smushes
+    fn overlap(&self, output: &[Vec<char>], glyph: &[Vec<char>], previous_width: usize, width: usize) -> usize {
+        if self.layout == Layout::FullWidth {
+            return 0;
+        }
+        let mut overlap = width;
+        for (line, row) in output.iter().zip(glyph) {
+            // Last visible column of the output and first of the glyph
+            let line_edge = line.iter().rposition(|&c| c != ' ');
+            let row_edge = row.iter().position(|&c| c != ' ');
+            let leading = row_edge.unwrap_or(row.len());
+
+            let amount = match line_edge {
+                Some(edge) => {
+                    let smushes = row_edge
+                        .is_some_and(|row_edge| self.smush(line[edge], row[row_edge], previous_width, width).is_some());
+                    leading + line.len() - edge - usize::from(!smushes)
+                }
+                None => leading + line.len(),
+            };
+            overlap = overlap.min(amount);
+        }
+        overlap
+    }
+
+    // Character replacing an overlapping pair, or `None` if the pair can't
+    // share a column
+    fn smush(&self, left: char, right: char, previous_width: usize, width: usize) -> Option<char> {
+        if left == ' ' {
+            return Some(right);
+        }
+        if right == ' ' {
+            return Some(left);
+        }
+        if previous_width < 2 || width < 2 {
+            return None;
+        }
+        let rules = match self.layout {
+            Layout::Smushing(rules) => rules,
+            _ => return None,
+        };
+        let hardblank = self.hardblank;
+
+        if rules == 0 {
+            // Universal smushing: the right character wins over visible ones
+            return Some(if left == hardblank {
+                right
+            } else if right == hardblank {
+                left
+            } else {
+                right
+            });
+        }
+        if left == hardblank || right == hardblank {
+            let both = left == hardblank && right == hardblank;
+            return (both && rules & SMUSH_HARDBLANK != 0).then_some(hardblank);
+        }
+        if rules & SMUSH_EQUAL != 0 && left == right {
+            return Some(left);
+        }
+        if rules & SMUSH_LOWLINE != 0 {
+            const BORDERS: &str = "|/\\[]{}()<>";
+            if left == '_' && BORDERS.contains(right) {
+                return Some(right);
+            }
+            if right == '_' && BORDERS.contains(left) {
+                return Some(left);
+            }
+        }
+        if rules & SMUSH_HIERARCHY != 0 {
+            let class = |c: char| ["|", "/\\", "[]", "{}", "()", "<>"].iter().position(|class| class.contains(c));
+            if let (Some(l), Some(r)) = (class(left), class(right)) {
+                if l != r {
+                    return Some(if l > r { left } else { right });
+                }
+            }
+        }
+        if rules & SMUSH_PAIR != 0 {
+            let pair = matches!(
+                (left, right),
+                ('[', ']') | (']', '[') | ('{', '}') | ('}', '{') | ('(', ')') | (')', '(')
+            );
+            if pair {
+                return Some('|');
+            }
+        }
+        if rules & SMUSH_BIG_X != 0 {
+            match (left, right) {
+                ('/', '\\') => return Some('|'),
+                ('\\', '/') => return Some('Y'),
+                ('>', '<') => return Some('X'),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+// Horizontal layout from the header's full_layout field
+fn layout_from_full(full_layout: i64) -> Layout {
+    let bits = full_layout.max(0) as u32;
+    if bits & LAYOUT_SMUSHING != 0 {
+        Layout::Smushing(bits & SMUSH_RULES)
+    } else if bits & LAYOUT_KERNING != 0 {
+        Layout::Kerning
+    } else {
+        Layout::FullWidth
+    }
+}
+
+// Horizontal layout from the older old_layout field
+fn layout_from_old(old_layout: i64) -> Layout {
+    match old_layout {
+        i64::MIN..=-1 => Layout::FullWidth,
+        0 => Layout::Kerning,
+        rules => Layout::Smushing(rules as u32 & SMUSH_RULES),
+    }
+}
+
+// Read `height` rows of one character with their endmarks removed, padded
+// to a common width; `None` at the end of the data
+fn read_glyph<'a>(lines: &mut impl Iterator<Item = &'a str>, height: usize) -> Result<Option<Vec<Vec<char>>>, String> {
+    let mut rows = Vec::with_capacity(height);
+    for row in 0..height {
+        let Some(line) = lines.next() else {
+            if row == 0 {
+                return Ok(None);
+            }
+            return Err("FIGlet font ends in the middle of a character".to_string());
+        };
+        let line = line.trim_end();
+        let row: Vec<char> = match line.chars().last() {
+            Some(endmark) => line.trim_end_matches(endmark).chars().collect(),
+            None => Vec::new(),
+        };
+        rows.push(row);
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, ' ');
+    }
+    Ok(Some(rows))
+}
+
+// Character code in decimal, 0x-prefixed hex or 0-prefixed octal
+fn parse_code(code: &str) -> Option<i64> {
+    let (negative, digits) = match code.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, code),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Render text with a FIGlet font, centered in a grid
+///
+/// Each line of `text` becomes one banner row. Characters the font lacks
+/// are skipped, and rows wider than `width` are cut off on the right.
+///
+/// # Arguments
+/// * `text` - Text to render; `\n` starts a new banner row
+/// * `font_bytes` - Contents of an uncompressed `.flf` font file
+/// * `width` - Grid width in characters
+/// * `height` - Grid height in lines
+///
+/// # Returns
+/// The banner padded to `width` x `height`
+#[wasm_bindgen]
+pub fn generate_text_with_font(text: &str, font_bytes: &[u8], width: usize, height: usize) -> Result<String, String> {
+    let font = FigletFont::parse(font_bytes)?;
+    let lines: Vec<String> = text.lines().flat_map(|line| font.render_line(line)).collect();
+    Ok(center_in_grid(&lines, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One-row font with the given glyphs; every other character is drawn
+    // as itself, and ' ' is two columns of blank
+    fn test_font(old_layout: i32, full_layout: Option<u32>, glyphs: &[(char, &str)]) -> Vec<u8> {
+        let full_layout = full_layout.map(|full| format!(" 0 {}", full)).unwrap_or_default();
+        let mut font = format!("flf2a$ 1 1 4 {} 1{}\nTest font\n", old_layout, full_layout);
+        for c in (32u32..=126).chain(DEUTSCH_CHARS).filter_map(char::from_u32) {
+            let row = match glyphs.iter().find(|(g, _)| *g == c) {
+                Some((_, row)) => row.to_string(),
+                None if c == ' ' => "  ".to_string(),
+                None => c.to_string(),
+            };
+            font.push_str(&format!("{}@@\n", row));
+        }
+        font.push_str("0x2192 RIGHTWARDS ARROW\n->@@\n");
+        font.into_bytes()
+    }
+
+    fn render(old_layout: i32, full_layout: Option<u32>, glyphs: &[(char, &str)], text: &str) -> String {
+        let font = FigletFont::parse(&test_font(old_layout, full_layout, glyphs)).unwrap();
+        font.render_line(text).concat()
+    }
+
+    #[test]
+    fn test_parses_header_and_tagged_characters() {
+        let font = FigletFont::parse(&test_font(-1, None, &[])).unwrap();
+        assert_eq!(font.hardblank, '$');
+        assert_eq!(font.height, 1);
+        assert_eq!(font.layout, Layout::FullWidth);
+        assert_eq!(font.glyphs.len(), 103);
+        assert_eq!(font.render_line("\u{e4}"), vec!["\u{e4}"]);
+        assert_eq!(font.render_line("a\u{2192}b"), vec!["a->b"]);
+
+        // full_layout overrides old_layout
+        let font = FigletFont::parse(&test_font(-1, Some(192), &[])).unwrap();
+        assert_eq!(font.layout, Layout::Smushing(0));
+
+        assert!(FigletFont::parse(b"not a font").is_err());
+        assert!(FigletFont::parse(b"flf2a$ 0 0 4 0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_full_width_and_kerning() {
+        let glyphs = [('a', "a "), ('b', " b"), ('f', "f$"), ('g', "$g")];
+        assert_eq!(render(-1, None, &glyphs, "ab"), "a  b");
+        assert_eq!(render(0, None, &glyphs, "ab"), "ab");
+        // Hardblanks are visible while kerning and print as spaces
+        assert_eq!(render(0, None, &glyphs, "fg"), "f  g");
+    }
+
+    #[test]
+    fn test_smushing_rules() {
+        let glyphs = [
+            ('a', "a|"),
+            ('b', "|b"),
+            ('c', "/c"),
+            ('d', "d/"),
+            ('e', "\\e"),
+            ('f', "f$"),
+            ('g', "$g"),
+            ('h', "h_"),
+            ('i', "[i"),
+            ('j', "j]"),
+        ];
+        assert_eq!(render(1, None, &glyphs, "ab"), "a|b");
+        assert_eq!(render(2, None, &glyphs, "hi"), "h[i");
+        assert_eq!(render(4, None, &glyphs, "ac"), "a/c");
+        assert_eq!(render(8, None, &glyphs, "ji"), "j|i");
+        assert_eq!(render(16, None, &glyphs, "de"), "d|e");
+        assert_eq!(render(32, None, &glyphs, "fg"), "f g");
+        // Pairs without a matching rule only kern
+        assert_eq!(render(1, None, &glyphs, "ac"), "a|/c");
+        // Universal smushing lets the right character win
+        assert_eq!(render(0, Some(128), &glyphs, "ac"), "a/c");
+    }
+}
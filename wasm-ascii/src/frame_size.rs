@@ -0,0 +1,48 @@
+// Size limit for frames and images allocated from caller-supplied sizes
+//
+// The cap is on the total rather than per side: a wasm32 heap runs out
+// long before any single side gets large. 16M cells is 64 MiB as RGBA
+// pixels, and at most 64 MiB of UTF-8 as text.
+
+pub(crate) const MAX_FRAME_AREA: usize = 4096 * 4096;
+
+/// Check that a `width` x `height` frame is non-empty and at most
+/// `MAX_FRAME_AREA` in total; `what` and `unit` word the error
+pub(crate) fn check_frame_size(what: &str, unit: &str, width: usize, height: usize) -> Result<(), String> {
+    match width.checked_mul(height) {
+        Some(area) if area > 0 && area <= MAX_FRAME_AREA => Ok(()),
+        _ => Err(format!(
+            "{} must be between 1 and {} {} in total, got {}x{}",
+            what, MAX_FRAME_AREA, unit, width, height
+        )),
+    }
+}
+
+/// Nearest size `check_frame_size` accepts: both sides at least 1, and the
+/// height cut until the frame fits
+///
+/// Only for entry points that can't return an error; the rest reject
+/// out-of-range sizes with `check_frame_size`.
+pub(crate) fn clamp_frame_size(width: usize, height: usize) -> (usize, usize) {
+    let width = width.clamp(1, MAX_FRAME_AREA);
+    (width, height.clamp(1, MAX_FRAME_AREA / width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_is_on_total_area() {
+        assert!(check_frame_size("Frame", "cells", 4096, 4096).is_ok());
+        assert!(check_frame_size("Frame", "cells", MAX_FRAME_AREA, 1).is_ok());
+        let err = check_frame_size("Frame", "cells", 8192, 4096).unwrap_err();
+        assert_eq!(err, "Frame must be between 1 and 16777216 cells in total, got 8192x4096");
+        assert!(check_frame_size("Frame", "cells", 0, 10).is_err());
+        assert!(check_frame_size("Frame", "cells", usize::MAX, 2).is_err());
+
+        assert_eq!(clamp_frame_size(0, 0), (1, 1));
+        assert_eq!(clamp_frame_size(8192, 8192), (8192, 2048));
+        assert_eq!(clamp_frame_size(80, 24), (80, 24));
+    }
+}
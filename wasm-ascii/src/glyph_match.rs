@@ -0,0 +1,219 @@
+use wasm_bindgen::prelude::*;
+
+use crate::font::{cell_mask, CELL_HEIGHT, CELL_WIDTH};
+
+const RASTER_LEN: usize = CELL_WIDTH * CELL_HEIGHT;
+
+// SSIM stabilizers for an 8-bit dynamic range
+const SSIM_C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Similarity measure used to pick the glyph that best matches a cell
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphMetric {
+    /// Mean squared error with extra weight on mean brightness
+    Mse,
+    /// Structural similarity over the whole cell
+    Ssim,
+}
+
+// A charset character rasterized at cell resolution
+#[derive(Debug)]
+pub(crate) struct GlyphTemplate {
+    ch: char,
+    raster: [f32; RASTER_LEN],
+    mean: f32,
+    variance: f32,
+}
+
+fn mean_and_variance(values: &[f32]) -> (f32, f32) {
+    let n = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / n;
+    (mean, variance)
+}
+
+// Rasterize every charset character into `templates`, unless they already
+// hold this charset; ink is bright (255) like the brightest end of the charset
+//
+// Characters the embedded font does not cover (e.g. block shades) become
+// flat tones at the brightness their charset position implies.
+fn update_templates(chars: &[char], templates: &mut Vec<GlyphTemplate>) {
+    if templates.len() == chars.len() && templates.iter().zip(chars).all(|(glyph, &ch)| glyph.ch == ch) {
+        return;
+    }
+
+    let steps = chars.len().saturating_sub(1).max(1) as f32;
+    templates.clear();
+    templates.extend(chars.iter().enumerate().map(|(i, &ch)| {
+        let raster = match cell_mask(ch) {
+            Some(mask) => mask.map(|ink| if ink { 255.0 } else { 0.0 }),
+            None => [i as f32 / steps * 255.0; RASTER_LEN],
+        };
+        let (mean, variance) = mean_and_variance(&raster);
+        GlyphTemplate { ch, raster, mean, variance }
+    }));
+}
+
+// Box-sample one cell of `gray` down (or up) to glyph raster resolution
+#[allow(clippy::too_many_arguments)]
+fn sample_patch(
+    gray: &[f64],
+    width: usize,
+    height: usize,
+    x0: usize,
+    y0: usize,
+    cell_w: usize,
+    cell_h: usize,
+    patch: &mut [f32; RASTER_LEN],
+) {
+    for ry in 0..CELL_HEIGHT {
+        let sy0 = (y0 + ry * cell_h / CELL_HEIGHT).min(height - 1);
+        let sy1 = (y0 + (ry + 1) * cell_h / CELL_HEIGHT).clamp(sy0 + 1, height);
+        for rx in 0..CELL_WIDTH {
+            let sx0 = (x0 + rx * cell_w / CELL_WIDTH).min(width - 1);
+            let sx1 = (x0 + (rx + 1) * cell_w / CELL_WIDTH).clamp(sx0 + 1, width);
+            let mut sum = 0.0;
+            for y in sy0..sy1 {
+                sum += gray[y * width + sx0..y * width + sx1].iter().sum::<f64>();
+            }
+            patch[ry * CELL_WIDTH + rx] = (sum / ((sy1 - sy0) * (sx1 - sx0)) as f64) as f32;
+        }
+    }
+}
+
+// Lower is better for both metrics
+fn match_cost(patch: &[f32; RASTER_LEN], mean: f32, variance: f32, glyph: &GlyphTemplate, metric: GlyphMetric) -> f32 {
+    match metric {
+        GlyphMetric::Mse => {
+            // A binary glyph has the same MSE against a flat gray patch no
+            // matter how much ink it has, so the mean is counted twice to
+            // keep flat regions at the right tone
+            let mse = patch
+                .iter()
+                .zip(&glyph.raster)
+                .map(|(p, g)| (p - g) * (p - g))
+                .sum::<f32>()
+                / RASTER_LEN as f32;
+            mse + (mean - glyph.mean) * (mean - glyph.mean)
+        }
+        GlyphMetric::Ssim => {
+            let covariance = patch
+                .iter()
+                .zip(&glyph.raster)
+                .map(|(p, g)| (p - mean) * (g - glyph.mean))
+                .sum::<f32>()
+                / RASTER_LEN as f32;
+            let ssim = ((2.0 * mean * glyph.mean + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                / ((mean * mean + glyph.mean * glyph.mean + SSIM_C1) * (variance + glyph.variance + SSIM_C2));
+            1.0 - ssim
+        }
+    }
+}
+
+/// Pick one character per `cell_w` x `cell_h` block of `gray` by shape
+///
+/// Writes the cells row-major into `cells` and returns the number of columns
+/// and rows; `templates` caches the rasterized charset between calls.
+/// Partial cells at the right and bottom edges are sampled from the pixels
+/// they cover. Cells whose pixels are all set in `blank` become spaces; an
+/// empty `blank` blanks nothing.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn glyph_match_cells(
+    gray: &[f64],
+    width: usize,
+    height: usize,
+    chars: &[char],
+    templates: &mut Vec<GlyphTemplate>,
+    cell_w: usize,
+    cell_h: usize,
+    metric: GlyphMetric,
+    blank: &[bool],
+    cells: &mut Vec<char>,
+) -> (usize, usize) {
+    update_templates(chars, templates);
+    let cols = width.div_ceil(cell_w);
+    let rows = height.div_ceil(cell_h);
+    cells.clear();
+    let mut patch = [0.0; RASTER_LEN];
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let (x0, y0) = (col * cell_w, row * cell_h);
+            let w = cell_w.min(width - x0);
+            let h = cell_h.min(height - y0);
+
+            if !blank.is_empty() && (y0..y0 + h).all(|y| blank[y * width + x0..y * width + x0 + w].iter().all(|&b| b)) {
+                cells.push(' ');
+                continue;
+            }
+
+            sample_patch(gray, width, height, x0, y0, w, h, &mut patch);
+            let (mean, variance) = mean_and_variance(&patch);
+            let best = templates
+                .iter()
+                .map(|glyph| (glyph.ch, match_cost(&patch, mean, variance, glyph, metric)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map_or(' ', |(ch, _)| ch);
+            cells.push(best);
+        }
+    }
+
+    (cols, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Render `c` into a gray image the size of one cell, scaled by `scale`
+    fn render_glyph(c: char, scale: usize) -> Vec<f64> {
+        let mask = cell_mask(c).unwrap();
+        let (w, h) = (CELL_WIDTH * scale, CELL_HEIGHT * scale);
+        (0..w * h)
+            .map(|i| if mask[(i / w / scale) * CELL_WIDTH + (i % w) / scale] { 255.0 } else { 0.0 })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_rendered_glyph_by_shape() {
+        // Similar ink coverage, different shapes
+        let chars: Vec<char> = " -|/\\".chars().collect();
+        for metric in [GlyphMetric::Mse, GlyphMetric::Ssim] {
+            for &c in &chars[1..] {
+                let gray = render_glyph(c, 2);
+                let mut cells = Vec::new();
+                let (cols, rows) = glyph_match_cells(
+                    &gray,
+                    CELL_WIDTH * 2,
+                    CELL_HEIGHT * 2,
+                    &chars,
+                    &mut Vec::new(),
+                    12,
+                    20,
+                    metric,
+                    &[],
+                    &mut cells,
+                );
+                assert_eq!((cols, rows), (1, 1));
+                assert_eq!(cells, vec![c], "{:?}", metric);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flat_patches_keep_their_tone() {
+        let chars: Vec<char> = " .:#".chars().collect();
+        let dark = vec![0.0; 60];
+        let bright = vec![255.0; 60];
+        let mut templates = Vec::new();
+        let mut match_one = |gray: &[f64]| {
+            let mut cells = Vec::new();
+            glyph_match_cells(gray, 6, 10, &chars, &mut templates, 6, 10, GlyphMetric::Mse, &[], &mut cells);
+            cells[0]
+        };
+        assert_eq!(match_one(&dark), ' ');
+        assert_eq!(match_one(&bright), '#');
+    }
+}
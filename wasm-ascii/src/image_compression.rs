@@ -1,8 +1,11 @@
 use wasm_bindgen::prelude::*;
-use image::{ImageFormat};
+use image::{DynamicImage, ImageDecoder, ImageFormat, RgbImage};
 use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
 use std::io::Cursor;
 
+use crate::palette::{encode_indexed_png, exact_palette, median_cut, MAX_PALETTE_COLORS};
+
 /// Image output format
 #[wasm_bindgen]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -63,46 +66,108 @@ impl CompressionConfig {
         }
     }
 
-    /// Determine if PNG should be converted to JPEG for better compression
+    /// Determine if PNG output may be lossy (median-cut palette or JPEG)
     /// 
-    /// For ASCII art, JPEG compresses much better than PNG (often 3-5x smaller).
-    /// However, we only auto-convert small images to avoid breaking user expectations
+    /// For ASCII art, lossy candidates can compress better than a lossless PNG.
+    /// However, we only allow them for small images to avoid breaking user expectations
     /// for large images where PNG might be specifically needed (transparency, lossless).
-    fn should_convert_png_to_jpeg(&self) -> bool {
-        // Only convert small PNGs to JPEG for better compression
-        // Large PNGs are kept as PNG (user might need lossless/transparency)
+    fn allows_lossy_png(&self) -> bool {
+        // Only small PNGs may become lossy
+        // Large PNGs are kept lossless (user might need lossless/transparency)
         self.format == ImageFormatType::Png 
             && self.pixel_count < 1_000_000 
             && self.original_size < 200_000
     }
 
-    /// Check if compression result is acceptable
-    fn is_compression_acceptable(&self, compressed_size: usize) -> bool {
-        if compressed_size >= self.original_size {
-            // Only reject if original was small (larger files might have overhead)
-            self.original_size >= match self.format {
-                ImageFormatType::Jpeg => 200_000,
-                ImageFormatType::Png => 500_000,
-            }
-        } else {
-            true
+}
+
+/// Caps on what an input image may claim before it is decoded
+///
+/// A few hundred bytes can declare a 50000x50000 image, so dimensions and
+/// the decode buffer are checked against these limits first.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_width: u32,
+    max_height: u32,
+    pub(crate) max_alloc: u32,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_width: 16_384,
+            max_height: 16_384,
+            // 256 MiB, a quarter of the 4 GiB WASM address space
+            max_alloc: 256 * 1024 * 1024,
         }
     }
 }
 
+#[wasm_bindgen]
+impl DecodeLimits {
+    /// 16384x16384 pixels and 256 MiB of decoded pixels
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> DecodeLimits {
+        Self::default()
+    }
+
+    pub fn with_max_dimensions(mut self, max_width: u32, max_height: u32) -> DecodeLimits {
+        self.max_width = max_width;
+        self.max_height = max_height;
+        self
+    }
+
+    /// Most bytes the decoder may allocate
+    pub fn with_max_alloc(mut self, max_alloc: u32) -> DecodeLimits {
+        self.max_alloc = max_alloc;
+        self
+    }
+}
+
+impl DecodeLimits {
+    pub(crate) fn check(&self, decoder: &impl ImageDecoder) -> Result<(), String> {
+        let (width, height) = decoder.dimensions();
+        if width > self.max_width || height > self.max_height {
+            return Err(format!(
+                "Image is {}x{} pixels, over the {}x{} decode limit",
+                width, height, self.max_width, self.max_height
+            ));
+        }
+        if decoder.total_bytes() > self.max_alloc as u64 {
+            return Err(format!(
+                "Decoding the {}x{} image needs {} bytes, over the {} byte limit",
+                width,
+                height,
+                decoder.total_bytes(),
+                self.max_alloc
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_image_limits(self) -> image::Limits {
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(self.max_width);
+        limits.max_image_height = Some(self.max_height);
+        limits.max_alloc = Some(self.max_alloc as u64);
+        limits
+    }
+}
+
 /// Compress and optimize an image optimized for ASCII art
 /// 
 /// ASCII art (text on solid backgrounds) compresses extremely well with aggressive settings:
 /// - Aggressive quality reduction (50-75% range) - text is very forgiving
 /// - Automatic quality adjustment based on image size
-/// - Smart format selection (JPEG preferred for ASCII art)
-/// - Safety checks to avoid making files larger
+/// - Smart format selection (smallest of palette PNG, full PNG and JPEG)
+/// - EXIF orientation applied, so phone photos come out upright
+/// - Output is always re-encoded and carries no EXIF or other metadata
 /// 
 /// Optimization strategy:
 /// - Small images (< 50KB): Very aggressive compression (50-65% quality)
 /// - Medium images (50-200KB): Moderate compression (60-70% quality)
 /// - Large images (> 200KB): Standard compression (65-75% quality)
-/// - Returns original if compression would make file larger
 /// 
 /// # Arguments
 /// * `image_data` - Raw image bytes (PNG, JPEG, etc.)
@@ -118,10 +183,22 @@ pub fn compress_image(
     max_width: u32,
     quality: u8,
     format: &str,
+) -> Result<Vec<u8>, String> {
+    compress_image_with_limits(image_data, max_width, quality, format, &DecodeLimits::default())
+}
+
+/// `compress_image` with explicit decode limits instead of the defaults
+#[wasm_bindgen]
+pub fn compress_image_with_limits(
+    image_data: &[u8],
+    max_width: u32,
+    quality: u8,
+    format: &str,
+    limits: &DecodeLimits,
 ) -> Result<Vec<u8>, String> {
     let format_type = ImageFormatType::from_str(format)?;
     
-    compress_image_internal(image_data, max_width, quality, format_type)
+    compress_image_internal(image_data, max_width, quality, format_type, limits)
 }
 
 /// Internal compression function with proper types
@@ -130,10 +207,10 @@ fn compress_image_internal(
     max_width: u32,
     quality: u8,
     format: ImageFormatType,
+    limits: &DecodeLimits,
 ) -> Result<Vec<u8>, String> {
-    // Decode the image
-    let img = image::load_from_memory(image_data)
-        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    // Decode the image upright
+    let img = decode_oriented(image_data, limits)?;
 
     // Calculate new dimensions while maintaining aspect ratio
     let (new_width, new_height) = calculate_dimensions(img.width(), img.height(), max_width);
@@ -153,29 +230,150 @@ fn compress_image_internal(
         pixel_count: (new_width * new_height) as usize,
     };
 
-    // Encode based on format
-    let output = match config.format {
-        ImageFormatType::Jpeg => encode_jpeg(&resized_img, &config)?,
-        ImageFormatType::Png => {
-            if config.should_convert_png_to_jpeg() {
-                // For small ASCII art images, convert PNG to JPEG for much better compression
-                // JPEG typically compresses ASCII art 3-5x better than PNG
-                // This is safe because ASCII art doesn't need transparency or lossless quality
-                encode_jpeg(&resized_img, &config)?
-            } else {
-                // For larger images, respect PNG format request
-                // User might need PNG for transparency, lossless quality, or other reasons
-                encode_png(&resized_img)?
-            }
+    // Encode based on format; never pass the input through, it may carry
+    // metadata and the wrong orientation
+    match config.format {
+        ImageFormatType::Jpeg => encode_jpeg(&resized_img, &config),
+        ImageFormatType::Png => encode_smallest_png(&resized_img, &config),
+    }
+}
+
+// Decode within `limits` and apply the EXIF orientation
+pub(crate) fn decode_oriented(image_data: &[u8], limits: &DecodeLimits) -> Result<DynamicImage, String> {
+    let mut decoder = open_decoder(image_data)?;
+    limits.check(&decoder)?;
+    decoder
+        .set_limits(limits.to_image_limits())
+        .map_err(|e| format!("Image exceeds decode limits: {}", e))?;
+    // A broken orientation tag shouldn't cost the whole image
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| match e {
+        image::ImageError::Limits(e) => format!("Image exceeds decode limits: {}", e),
+        e => format!("Failed to decode image: {}", e),
+    })?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+// Decoder with the format guessed from the bytes; limits are left to the caller
+fn open_decoder(image_data: &[u8]) -> Result<impl ImageDecoder + '_, String> {
+    let mut reader = image::ImageReader::new(Cursor::new(image_data))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to create image reader: {}", e))?;
+    reader.no_limits();
+    reader
+        .into_decoder()
+        .map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+/// Image bytes produced by `compress_image_to_size` with the settings that
+/// achieved them
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SizedImage {
+    data: Vec<u8>,
+    quality: u8,
+    width: u32,
+    height: u32,
+}
+
+#[wasm_bindgen]
+impl SizedImage {
+    /// JPEG bytes, at most the requested budget
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    /// JPEG quality the image was encoded with
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Compress an image to a JPEG of at most `max_bytes`
+///
+/// Binary-searches the highest JPEG quality that fits the budget. If even
+/// `min_quality` is too large, the image is scaled down by a quarter per
+/// side and the search repeats, until it fits. Input is decoded upright
+/// under the default `DecodeLimits`.
+///
+/// # Arguments
+/// * `image_data` - Raw image bytes (PNG, JPEG, etc.)
+/// * `max_bytes` - Hard limit on the output size
+/// * `max_width` - Maximum width in pixels (maintains aspect ratio); 0 keeps the source width
+/// * `min_quality` - Lowest JPEG quality (1-100) worth keeping before shrinking the image
+///
+/// # Returns
+/// The compressed bytes with the quality and dimensions they were encoded at
+#[wasm_bindgen]
+pub fn compress_image_to_size(
+    image_data: &[u8],
+    max_bytes: usize,
+    max_width: u32,
+    min_quality: u8,
+) -> Result<SizedImage, String> {
+    if !(1..=100).contains(&min_quality) {
+        return Err(format!("Minimum quality must be between 1 and 100, got {}", min_quality));
+    }
+
+    let img = decode_oriented(image_data, &DecodeLimits::default())?;
+    let (mut width, mut height) = calculate_dimensions(img.width(), img.height(), max_width);
+
+    loop {
+        let resized = if (width, height) == (img.width(), img.height()) {
+            img.to_rgb8()
+        } else {
+            img.resize_exact(width, height, image::imageops::FilterType::Lanczos3).to_rgb8()
+        };
+
+        if let Some((data, quality)) = best_quality_within(&resized, max_bytes, min_quality)? {
+            return Ok(SizedImage { data, quality, width, height });
         }
-    };
+        if width == 1 && height == 1 {
+            return Err(format!(
+                "Cannot compress image to {} bytes, even at 1x1 pixels and quality {}",
+                max_bytes, min_quality
+            ));
+        }
+        width = (width * 3 / 4).max(1);
+        height = (height * 3 / 4).max(1);
+    }
+}
 
-    // Verify compression is acceptable
-    if !config.is_compression_acceptable(output.len()) {
-        return Ok(image_data.to_vec());
+// Highest quality in `min_quality..=100` whose JPEG fits in `max_bytes`,
+// with its bytes; `None` if even `min_quality` is too large
+fn best_quality_within(
+    rgb_img: &RgbImage,
+    max_bytes: usize,
+    min_quality: u8,
+) -> Result<Option<(Vec<u8>, u8)>, String> {
+    let smallest = encode_jpeg_with_quality(rgb_img, min_quality)?;
+    if smallest.len() > max_bytes {
+        return Ok(None);
     }
 
-    Ok(output)
+    // `best` always fits; qualities above `high` never do
+    let mut best = (smallest, min_quality);
+    let mut high = 100;
+    while best.1 < high {
+        let quality = best.1 + (high - best.1).div_ceil(2);
+        let output = encode_jpeg_with_quality(rgb_img, quality)?;
+        if output.len() <= max_bytes {
+            best = (output, quality);
+        } else {
+            high = quality - 1;
+        }
+    }
+    Ok(Some(best))
 }
 
 /// Calculate new dimensions while maintaining aspect ratio
@@ -193,11 +391,13 @@ fn encode_jpeg(
     img: &image::DynamicImage,
     config: &CompressionConfig,
 ) -> Result<Vec<u8>, String> {
-    let rgb_img = img.to_rgb8();
-    let final_quality = config.calculate_final_quality();
-    
+    encode_jpeg_with_quality(&img.to_rgb8(), config.calculate_final_quality())
+}
+
+/// Encode RGB pixels as JPEG at an explicit quality (1-100)
+fn encode_jpeg_with_quality(rgb_img: &RgbImage, quality: u8) -> Result<Vec<u8>, String> {
     let mut output = Vec::new();
-    let mut encoder = JpegEncoder::new_with_quality(&mut output, final_quality);
+    let mut encoder = JpegEncoder::new_with_quality(&mut output, quality);
     encoder
         .encode(
             rgb_img.as_raw(),
@@ -210,6 +410,35 @@ fn encode_jpeg(
     Ok(output)
 }
 
+/// Encode the smallest PNG candidate
+///
+/// ASCII renders usually have only a handful of colors, so an indexed PNG
+/// keeps text edges crisp and is often smaller than a JPEG. Candidates are
+/// a palette PNG if the image has at most 256 colors (otherwise a full
+/// PNG), plus a median-cut palette PNG and a JPEG for small images.
+fn encode_smallest_png(
+    img: &image::DynamicImage,
+    config: &CompressionConfig,
+) -> Result<Vec<u8>, String> {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let exact = exact_palette(rgba.as_raw(), width, height, MAX_PALETTE_COLORS);
+
+    let mut candidates = vec![match &exact {
+        Some(indexed) => encode_indexed_png(indexed)?,
+        None => encode_png(img)?,
+    }];
+    if config.allows_lossy_png() {
+        if exact.is_none() {
+            let quantized = median_cut(rgba.as_raw(), width, height, MAX_PALETTE_COLORS);
+            candidates.push(encode_indexed_png(&quantized)?);
+        }
+        candidates.push(encode_jpeg(img, config)?);
+    }
+
+    Ok(candidates.into_iter().min_by_key(Vec::len).unwrap_or_default())
+}
+
 /// Encode image as PNG
 fn encode_png(img: &image::DynamicImage) -> Result<Vec<u8>, String> {
     let mut output = Vec::new();
@@ -221,15 +450,144 @@ fn encode_png(img: &image::DynamicImage) -> Result<Vec<u8>, String> {
 }
 
 /// Get image dimensions without decoding the full image
+///
+/// Dimensions are after EXIF orientation, i.e. as `compress_image` outputs them.
 #[wasm_bindgen]
 pub fn get_image_dimensions(image_data: &[u8]) -> Result<Vec<u32>, String> {
-    // Use image crate to get dimensions (it's efficient for header reading)
-    let reader = image::ImageReader::new(Cursor::new(image_data))
-        .with_guessed_format()
-        .map_err(|e| format!("Failed to create image reader: {}", e))?;
-    
-    let (width, height) = reader.into_dimensions()
-        .map_err(|e| format!("Failed to get dimensions: {}", e))?;
+    // Only the header is read, so this is cheap even for huge images
+    let mut decoder = open_decoder(image_data)?;
+    let (width, height) = decoder.dimensions();
+
+    match decoder.orientation().unwrap_or(Orientation::NoTransforms) {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => Ok(vec![height, width]),
+        _ => Ok(vec![width, height]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Noisy 96x64 PNG that JPEG can't squeeze much at high quality
+    fn noisy_png() -> Vec<u8> {
+        let img = RgbImage::from_fn(96, 64, |x, y| {
+            let v = ((x * 7919 + y * 104_729) ^ (x * y * 31)) as u8;
+            image::Rgb([v, v.wrapping_mul(3), v.wrapping_add(x as u8)])
+        });
+        let mut output = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut output), ImageFormat::Png)
+            .unwrap();
+        output
+    }
+
+    // 16x8 JPEG, red on the left and blue on the right, tagged as needing
+    // a 90 degree clockwise turn (EXIF orientation 6) like a portrait phone photo
+    fn sideways_jpeg() -> Vec<u8> {
+        use image::ImageEncoder;
+
+        let img = RgbImage::from_fn(16, 8, |x, _| {
+            if x < 8 { image::Rgb([220, 20, 20]) } else { image::Rgb([20, 20, 220]) }
+        });
+        // Little-endian TIFF header and one IFD entry: Orientation (0x0112) = 6
+        let exif = vec![
+            b'I', b'I', 42, 0, 8, 0, 0, 0, // header, IFD at offset 8
+            1, 0, // one entry
+            0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, // SHORT, count 1, value 6
+            0, 0, 0, 0, // no next IFD
+        ];
+        let mut output = Vec::new();
+        let mut encoder = JpegEncoder::new_with_quality(&mut output, 95);
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder
+            .write_image(img.as_raw(), 16, 8, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn test_exif_orientation_applied_and_stripped() {
+        let jpeg = sideways_jpeg();
+        assert_eq!(get_image_dimensions(&jpeg).unwrap(), vec![8, 16]);
+
+        for format in ["jpeg", "png"] {
+            let output = compress_image(&jpeg, 0, 90, format).unwrap();
+            let mut decoder = open_decoder(&output).unwrap();
+            assert_eq!(decoder.exif_metadata().unwrap(), None);
+            let upright = image::load_from_memory(&output).unwrap().to_rgb8();
+            assert_eq!(upright.dimensions(), (8, 16));
+            // The left edge ends up on top
+            assert!(upright.get_pixel(4, 3)[0] > 150 && upright.get_pixel(4, 3)[2] < 100);
+            assert!(upright.get_pixel(4, 12)[2] > 150 && upright.get_pixel(4, 12)[0] < 100);
+        }
+    }
+
+    #[test]
+    fn test_decode_limits_reject_oversized_images() {
+        let png = noisy_png();
+        let small = DecodeLimits::new().with_max_dimensions(64, 64);
+        let err = compress_image_with_limits(&png, 0, 80, "jpeg", &small).unwrap_err();
+        assert_eq!(err, "Image is 96x64 pixels, over the 64x64 decode limit");
+
+        let tight = DecodeLimits::new().with_max_alloc(1_000);
+        let err = compress_image_with_limits(&png, 0, 80, "png", &tight).unwrap_err();
+        assert!(err.contains("needs 18432 bytes, over the 1000 byte limit"), "{}", err);
+
+        assert!(compress_image_with_limits(&png, 0, 80, "jpeg", &DecodeLimits::new()).is_ok());
+    }
+
+    #[test]
+    fn test_few_color_png_stays_lossless_and_indexed() {
+        // Dark text-like strokes on a light background
+        let img = RgbImage::from_fn(120, 60, |x, y| {
+            if (x / 3 + y / 5) % 4 == 0 && y % 10 < 7 {
+                image::Rgb([20, 20, 20])
+            } else {
+                image::Rgb([240, 240, 230])
+            }
+        });
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(img.clone())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
 
-    Ok(vec![width, height])
+        let output = compress_image(&png, 0, 80, "png").unwrap();
+        assert_eq!(image::guess_format(&output).unwrap(), ImageFormat::Png);
+        assert_eq!(image::load_from_memory(&output).unwrap().to_rgb8(), img);
+        let jpeg = compress_image(&png, 0, 80, "jpeg").unwrap();
+        assert!(output.len() < jpeg.len());
+    }
+
+    #[test]
+    fn test_fits_budget_with_highest_quality() {
+        let png = noisy_png();
+        let roomy = compress_image_to_size(&png, 1_000_000, 0, 10).unwrap();
+        assert_eq!((roomy.quality(), roomy.width(), roomy.height()), (100, 96, 64));
+
+        let tight = compress_image_to_size(&png, 6_000, 0, 10).unwrap();
+        assert!(tight.data().len() <= 6_000);
+        assert!(tight.quality() < 100);
+        assert_eq!((tight.width(), tight.height()), (96, 64));
+        // One step up in quality would no longer fit
+        let rgb = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert!(encode_jpeg_with_quality(&rgb, tight.quality() + 1).unwrap().len() > 6_000);
+        let decoded = image::load_from_memory(&tight.data()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (96, 64));
+    }
+
+    #[test]
+    fn test_shrinks_when_quality_alone_is_not_enough() {
+        let png = noisy_png();
+        // Quality 90 needs about 2.6KB at 48x32 but fits once scaled to 36x24
+        let shrunk = compress_image_to_size(&png, 2_000, 48, 90).unwrap();
+        assert!(shrunk.data().len() <= 2_000);
+        assert_eq!((shrunk.width(), shrunk.height()), (36, 24));
+        assert!(shrunk.quality() >= 90);
+
+        assert!(compress_image_to_size(&png, 10, 0, 50).is_err());
+        assert!(compress_image_to_size(&png, 10_000, 0, 0).is_err());
+    }
 }
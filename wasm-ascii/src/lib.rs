@@ -4,6 +4,9 @@ use wasm_bindgen::prelude::*;
 mod image_compression;
 pub use image_compression::*;
 
+// Palette quantization and indexed PNG encoding
+mod palette;
+
 // Sphere animation module
 mod sphere_animation;
 pub use sphere_animation::*;
@@ -12,187 +15,99 @@ pub use sphere_animation::*;
 mod text_in_center;
 pub use text_in_center::*;
 
-// Helper function to clamp values
-#[inline]
-fn clamp(value: f64, min: f64, max: f64) -> f64 {
-    value.max(min).min(max)
-}
+// Colored output module
+mod color_output;
+pub use color_output::*;
 
-// Convert RGB to grayscale luminance
-#[inline]
-fn rgb_to_luminance(r: u8, g: u8, b: u8) -> f64 {
-    0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
-}
+// Conversion options module
+mod convert_options;
+pub use convert_options::*;
 
-// Generate a normalized 2D Gaussian kernel
-fn gaussian_kernel_2d(sigma: f64, kernel_size: usize) -> Vec<Vec<f64>> {
-    let half = kernel_size / 2;
-    let mut kernel = vec![vec![0.0; kernel_size]; kernel_size];
-    let mut sum = 0.0;
-
-    for y in 0..kernel_size {
-        for x in 0..kernel_size {
-            let dy = y as i32 - half as i32;
-            let dx = x as i32 - half as i32;
-            let value = (-(dx * dx + dy * dy) as f64 / (2.0 * sigma * sigma)).exp();
-            kernel[y][x] = value;
-            sum += value;
-        }
-    }
+// DoG contour pipeline
+mod contour;
+use contour::{generate_contour_cells, ContourBuffers};
 
-    // Normalize the kernel
-    for y in 0..kernel_size {
-        for x in 0..kernel_size {
-            kernel[y][x] /= sum;
-        }
-    }
+// Embedded 5x7 bitmap font
+mod font;
 
-    kernel
-}
+// Size limit for generated frames and images
+mod frame_size;
 
-// Convolve a 2D image with a 2D kernel
-fn convolve_2d(img: &[Vec<f64>], kernel: &[Vec<f64>]) -> Vec<Vec<f64>> {
-    let height = img.len();
-    let width = img[0].len();
-    let kernel_size = kernel.len();
-    let half = kernel_size / 2;
-    let mut output = vec![vec![0.0; width]; height];
-
-    for y in 0..height {
-        for x in 0..width {
-            let mut sum = 0.0;
-            for ky in 0..kernel_size {
-                for kx in 0..kernel_size {
-                    let yy = y as i32 + ky as i32 - half as i32;
-                    let xx = x as i32 + kx as i32 - half as i32;
-                    let pixel = if yy >= 0 && yy < height as i32 && xx >= 0 && xx < width as i32 {
-                        img[yy as usize][xx as usize]
-                    } else {
-                        0.0
-                    };
-                    sum += pixel * kernel[ky][kx];
-                }
-            }
-            output[y][x] = sum;
-        }
-    }
+// Alpha compositing and blank pixels
+mod background;
+use background::{blank_pixels, composite_over};
 
-    output
-}
+// Gamma, equalization and auto-levels
+mod tone;
+pub use tone::Equalization;
+use tone::apply_tone;
 
-// Difference of Gaussians
-fn difference_of_gaussians_2d(
-    gray: &[Vec<f64>],
-    sigma1: f64,
-    sigma2: f64,
-    kernel_size: usize,
-) -> Vec<Vec<f64>> {
-    let kernel1 = gaussian_kernel_2d(sigma1, kernel_size);
-    let kernel2 = gaussian_kernel_2d(sigma2, kernel_size);
-    let blurred1 = convolve_2d(gray, &kernel1);
-    let blurred2 = convolve_2d(gray, &kernel2);
-    let height = gray.len();
-    let width = gray[0].len();
-    let mut dog = vec![vec![0.0; width]; height];
-
-    for y in 0..height {
-        for x in 0..width {
-            dog[y][x] = blurred1[y][x] - blurred2[y][x];
-        }
-    }
+// Dithering algorithms
+mod blue_noise;
+mod dithering;
+mod rng;
+use dithering::apply_dithering;
 
-    dog
-}
+// Glyph-shape matching renderer
+mod glyph_match;
+pub use glyph_match::GlyphMetric;
+use glyph_match::{glyph_match_cells, GlyphTemplate};
 
-// Apply Sobel operator to 2D image
-fn apply_sobel_2d(img: &[Vec<f64>], width: usize, height: usize) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
-    let kernel_x = vec![
-        vec![-1.0, 0.0, 1.0],
-        vec![-2.0, 0.0, 2.0],
-        vec![-1.0, 0.0, 1.0],
-    ];
-    let kernel_y = vec![
-        vec![-1.0, -2.0, -1.0],
-        vec![0.0, 0.0, 0.0],
-        vec![1.0, 2.0, 1.0],
-    ];
-
-    let mut mag = vec![vec![0.0; width]; height];
-    let mut angle = vec![vec![0.0; width]; height];
+// Charset density calibration
+mod charset;
+pub use charset::*;
 
-    for y in 1..height - 1 {
-        for x in 1..width - 1 {
-            let mut gx = 0.0;
-            let mut gy = 0.0;
-
-            for ky in 0..3 {
-                for kx in 0..3 {
-                    let py = (y + ky) as i32 - 1;
-                    let px = (x + kx) as i32 - 1;
-                    let pixel = img[py as usize][px as usize];
-                    gx += pixel * kernel_x[ky][kx];
-                    gy += pixel * kernel_y[ky][kx];
-                }
-            }
+// Braille and block-element sub-cell renderers
+mod subcell;
+use subcell::{half_block_colors, subcell_cells, subcell_size};
 
-            mag[y][x] = (gx * gx + gy * gy).sqrt();
-            let mut theta = gy.atan2(gx) * 180.0 / std::f64::consts::PI;
-            if theta < 0.0 {
-                theta += 180.0;
-            }
-            angle[y][x] = theta;
-        }
-    }
+// ASCII art to PNG/SVG rendering
+mod rasterize;
+pub use rasterize::*;
 
-    (mag, angle)
-}
+// Animated GIF/APNG conversion
+mod animation;
+pub use animation::*;
 
-// Non-maximum suppression
-fn non_max_suppression(
-    mag: &[Vec<f64>],
-    angle: &[Vec<f64>],
-    width: usize,
-    height: usize,
-) -> Vec<Vec<f64>> {
-    let mut suppressed = vec![vec![0.0; width]; height];
+// Stateful converter for video streams
+mod converter;
+pub use converter::*;
 
-    for y in 1..height - 1 {
-        for x in 1..width - 1 {
-            let current_mag = mag[y][x];
-            let theta = angle[y][x];
-            let (neighbor1, neighbor2) = if (theta >= 0.0 && theta < 22.5) || (theta >= 157.5 && theta <= 180.0) {
-                // 0° direction
-                (mag[y][x - 1], mag[y][x + 1])
-            } else if theta >= 22.5 && theta < 67.5 {
-                // 45° direction
-                (mag[y - 1][x + 1], mag[y + 1][x - 1])
-            } else if theta >= 67.5 && theta < 112.5 {
-                // 90° direction
-                (mag[y - 1][x], mag[y + 1][x])
-            } else {
-                // 135° direction
-                (mag[y - 1][x - 1], mag[y + 1][x + 1])
-            };
-
-            suppressed[y][x] = if current_mag >= neighbor1 && current_mag >= neighbor2 {
-                current_mag
-            } else {
-                0.0
-            };
-        }
-    }
+// FIGlet font banners
+mod figlet;
+pub use figlet::*;
+
+// Procedural effects
+mod effects;
+mod noise;
+pub use effects::*;
+
+// Ray-marched 3D primitives
+mod raymarch;
+pub use raymarch::*;
+
+// Helper function to clamp values
+#[inline]
+fn clamp(value: f64, min: f64, max: f64) -> f64 {
+    value.max(min).min(max)
+}
 
-    suppressed
+// Convert RGB to grayscale luminance
+#[inline]
+fn rgb_to_luminance(r: u8, g: u8, b: u8) -> f64 {
+    0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
 }
 
-// Apply Sobel edge detection on 1D grayscale array
+// Apply Sobel edge detection on 1D grayscale array, writing into `edges`
 fn apply_sobel_edge_detection(
     gray: &[f64],
+    edges: &mut Vec<f64>,
     width: usize,
     height: usize,
     threshold: f64,
-) -> Vec<f64> {
-    let mut edges = vec![255.0; width * height];
+) {
+    edges.clear();
+    edges.resize(width * height, 255.0);
 
     for y in 1..height - 1 {
         for x in 1..width - 1 {
@@ -217,223 +132,299 @@ fn apply_sobel_edge_detection(
             edges[idx] = if normalized > threshold { 0.0 } else { 255.0 };
         }
     }
+}
 
-    edges
+// Scratch memory for one conversion
+//
+// `AsciiConverter` keeps one across frames so the per-pixel buffers are
+// only allocated when the frame size grows.
+#[derive(Debug, Default)]
+struct FrameBuffers {
+    // Source pixels composited over the background; every stage reads these
+    composited: Vec<u8>,
+    // Adjusted grayscale, kept intact for the whole conversion
+    gray: Vec<f64>,
+    // Copy of `gray` that edge detection and dithering overwrite
+    work: Vec<f64>,
+    lit: Vec<bool>,
+    // Pixels to draw as spaces; empty if the options blank none
+    blank: Vec<bool>,
+    charset: Vec<char>,
+    templates: Vec<GlyphTemplate>,
+    cells: Vec<char>,
+    edges: Vec<char>,
+    // Per-cell text and, for half blocks, background colors
+    colors: Vec<[u8; 3]>,
+    backgrounds: Vec<[u8; 3]>,
+    contour: ContourBuffers,
 }
 
-// Floyd-Steinberg dithering
-fn apply_floyd_steinberg_dithering(
-    gray: &[f64],
+// Layout of the cells a conversion leaves in `FrameBuffers::cells`
+struct CellGrid {
+    cols: usize,
+    rows: usize,
+    // Source pixels covered by each cell
+    cell_width: usize,
+    cell_height: usize,
+}
+
+// Convert the composited pixels in `buffers` to a grid of characters,
+// row-major
+fn convert_to_grid(
     width: usize,
     height: usize,
-    n_levels: usize,
-) -> Vec<f64> {
-    let mut result = gray.to_vec();
-
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            let computed_level = ((result[idx] / 255.0) * (n_levels - 1) as f64).round() as usize;
-            let new_pixel = (computed_level as f64 / (n_levels - 1) as f64) * 255.0;
-            let error = result[idx] - new_pixel;
-
-            result[idx] = new_pixel;
+    options: &ConvertOptions,
+    buffers: &mut FrameBuffers,
+) -> CellGrid {
+    adjusted_grayscale_into(&buffers.composited, width, height, options, &mut buffers.gray);
+    blank_pixels(&buffers.composited, &buffers.gray, options, &mut buffers.blank);
+    buffers.charset.clear();
+    buffers.charset.extend(options.charset.chars());
+
+    if options.render_mode == RenderMode::GlyphMatch {
+        let (cols, rows) = glyph_match_cells(
+            &buffers.gray,
+            width,
+            height,
+            &buffers.charset,
+            &mut buffers.templates,
+            options.cell_width,
+            options.cell_height,
+            options.glyph_metric,
+            &buffers.blank,
+            &mut buffers.cells,
+        );
+        return CellGrid { cols, rows, cell_width: options.cell_width, cell_height: options.cell_height };
+    }
 
-            if x + 1 < width {
-                result[idx + 1] = clamp(result[idx + 1] + error * (7.0 / 16.0), 0.0, 255.0);
-            }
-            if x > 0 && y + 1 < height {
-                result[idx - 1 + width] = clamp(
-                    result[idx - 1 + width] + error * (3.0 / 16.0),
-                    0.0,
-                    255.0,
-                );
-            }
-            if y + 1 < height {
-                result[idx + width] = clamp(result[idx + width] + error * (5.0 / 16.0), 0.0, 255.0);
-            }
-            if x + 1 < width && y + 1 < height {
-                result[idx + width + 1] = clamp(
-                    result[idx + width + 1] + error * (1.0 / 16.0),
-                    0.0,
-                    255.0,
-                );
-            }
+    if options.render_mode.is_subcell() {
+        // Each sub-pixel is a binary decision, dithered when requested
+        buffers.work.clone_from(&buffers.gray);
+        apply_dithering(
+            &mut buffers.work,
+            width,
+            height,
+            2,
+            options.dither_algorithm,
+            options.serpentine,
+            options.seed,
+        );
+        buffers.lit.clear();
+        buffers.lit.extend(buffers.work.iter().map(|&v| v >= 128.0));
+        for (lit, &blank) in buffers.lit.iter_mut().zip(&buffers.blank) {
+            *lit &= !blank;
         }
+        let (cols, rows) = subcell_cells(&buffers.lit, width, height, options.render_mode, &mut buffers.cells);
+        let (cell_width, cell_height) = subcell_size(options.render_mode);
+        return CellGrid { cols, rows, cell_width, cell_height };
     }
 
-    result
+    convert_to_cells(width, height, options, buffers);
+    for (cell, &blank) in buffers.cells.iter_mut().zip(&buffers.blank) {
+        if blank {
+            *cell = ' ';
+        }
+    }
+    CellGrid {
+        cols: width,
+        rows: height,
+        cell_width: 1,
+        cell_height: 1,
+    }
 }
 
-// Atkinson dithering
-fn apply_atkinson_dithering(
-    gray: &[f64],
+// Convert the grayscale in `buffers` to one character per pixel, row-major
+fn convert_to_cells(
     width: usize,
     height: usize,
-    n_levels: usize,
-) -> Vec<f64> {
-    let mut result = gray.to_vec();
-
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            let computed_level = ((result[idx] / 255.0) * (n_levels - 1) as f64).round() as usize;
-            let new_pixel = (computed_level as f64 / (n_levels - 1) as f64) * 255.0;
-            let error = result[idx] - new_pixel;
-            let diffusion = error / 8.0;
-
-            result[idx] = new_pixel;
+    options: &ConvertOptions,
+    buffers: &mut FrameBuffers,
+) {
+    // Special handling for DoG contour mode
+    if options.edge_method == EdgeMethod::Dog {
+        generate_contour_cells(&buffers.gray, width, height, options, &mut buffers.contour, &mut buffers.cells);
+        return;
+    }
 
-            if x + 1 < width {
-                result[idx + 1] = clamp(result[idx + 1] + diffusion, 0.0, 255.0);
-            }
-            if x + 2 < width {
-                result[idx + 2] = clamp(result[idx + 2] + diffusion, 0.0, 255.0);
-            }
-            if y + 1 < height {
-                if x > 0 {
-                    result[idx - 1 + width] = clamp(result[idx - 1 + width] + diffusion, 0.0, 255.0);
-                }
-                result[idx + width] = clamp(result[idx + width] + diffusion, 0.0, 255.0);
-                if x + 1 < width {
-                    result[idx + width + 1] = clamp(result[idx + width + 1] + diffusion, 0.0, 255.0);
-                }
-            }
-            if y + 2 < height {
-                result[idx + 2 * width] = clamp(result[idx + 2 * width] + diffusion, 0.0, 255.0);
+    luminance_cells(
+        &buffers.gray,
+        &mut buffers.work,
+        &buffers.charset,
+        width,
+        height,
+        options,
+        &mut buffers.cells,
+    );
+
+    // Hybrid mode: directional glyphs replace the fill wherever a contour passes
+    if options.edge_method == EdgeMethod::Hybrid {
+        generate_contour_cells(&buffers.gray, width, height, options, &mut buffers.contour, &mut buffers.edges);
+        for (cell, &edge) in buffers.cells.iter_mut().zip(&buffers.edges) {
+            if edge != ' ' {
+                *cell = edge;
             }
         }
     }
-
-    result
 }
 
-// Noise dithering
-// Note: For proper random noise, you'd need to add the `rand` crate
-// This is a deterministic approximation using pixel-based pseudo-randomness
-fn apply_noise_dithering(
-    gray: &[f64],
-    _width: usize,
-    _height: usize,
-    n_levels: usize,
-) -> Vec<f64> {
-    gray.iter()
-        .enumerate()
-        .map(|(i, &pixel)| {
-            // Simple pseudo-random noise based on index and pixel value
-            let seed = (i as f64 * 17.0 + pixel) % 1000.0;
-            let noise = ((seed / 1000.0) - 0.5) * (255.0 / n_levels as f64);
-            let noisy_value = clamp(pixel + noise, 0.0, 255.0);
-            ((noisy_value / 255.0) * (n_levels - 1) as f64).round() * (255.0 / (n_levels - 1) as f64)
-        })
-        .collect()
+// Grayscale with invert, brightness/contrast and the tone stage applied,
+// written into `gray`
+fn adjusted_grayscale_into(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    options: &ConvertOptions,
+    gray: &mut Vec<f64>,
+) {
+    let ConvertOptions { invert, brightness, contrast, .. } = *options;
+    let contrast_factor = (259.0 * (contrast + 255.0)) / (255.0 * (259.0 - contrast));
+
+    gray.clear();
+    gray.extend(data.chunks_exact(4).map(|pixel| {
+        let mut lum = rgb_to_luminance(pixel[0], pixel[1], pixel[2]);
+        if invert {
+            lum = 255.0 - lum;
+        }
+        clamp(contrast_factor * (lum - 128.0) + 128.0 + brightness, 0.0, 255.0)
+    }));
+    apply_tone(gray, width, height, options);
 }
 
-// Ordered dithering (Bayer matrix)
-fn apply_ordered_dithering(
+// Map each pixel's brightness to a character of `chars`
+fn luminance_cells(
     gray: &[f64],
+    work: &mut Vec<f64>,
+    chars: &[char],
     width: usize,
     height: usize,
-    n_levels: usize,
-) -> Vec<f64> {
-    let bayer = vec![
-        vec![0, 8, 2, 10],
-        vec![12, 4, 14, 6],
-        vec![3, 11, 1, 9],
-        vec![15, 7, 13, 5],
-    ];
-    let matrix_size = 4;
-    let mut result = Vec::with_capacity(gray.len());
-
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            let p = gray[idx] / 255.0;
-            let t = (bayer[y % matrix_size][x % matrix_size] as f64 + 0.5)
-                / (matrix_size * matrix_size) as f64;
-            let mut value_with_dither = p + t - 0.5;
-            value_with_dither = value_with_dither.max(0.0).min(1.0);
-            let mut computed_level = (value_with_dither * n_levels as f64) as usize;
-            if computed_level >= n_levels {
-                computed_level = n_levels - 1;
-            }
-            result.push((computed_level as f64 / (n_levels - 1) as f64) * 255.0);
-        }
+    options: &ConvertOptions,
+    cells: &mut Vec<char>,
+) {
+    // Apply Sobel edge detection if enabled
+    if options.edge_method == EdgeMethod::Sobel {
+        apply_sobel_edge_detection(gray, work, width, height, options.edge_threshold);
+    } else {
+        work.clear();
+        work.extend_from_slice(gray);
     }
 
-    result
+    let n_levels = chars.len();
+
+    // Apply dithering if enabled
+    if options.dithering_enabled() {
+        apply_dithering(
+            work,
+            width,
+            height,
+            n_levels,
+            options.dither_algorithm,
+            options.serpentine,
+            options.seed,
+        );
+    }
+
+    // Convert to ASCII cells
+    cells.clear();
+    cells.extend(work.iter().map(|&v| {
+        let computed_level = ((v / 255.0) * (n_levels - 1) as f64).round() as usize;
+        chars[computed_level.min(n_levels - 1)]
+    }));
 }
 
-// Generate contour ASCII using DoG
-fn generate_contour_ascii(
-    data: &[u8],
+// Color the cells left in `buffers` by `convert_to_grid` and join them
+// into the output text
+fn render_grid(
     width: usize,
     height: usize,
-    invert: bool,
-    brightness: f64,
-    contrast: f64,
-    threshold: f64,
+    grid: &CellGrid,
+    options: &ConvertOptions,
+    buffers: &mut FrameBuffers,
 ) -> String {
-    // Convert to 2D grayscale
-    let contrast_factor = (259.0 * (contrast + 255.0)) / (255.0 * (259.0 - contrast));
-    let mut gray_2d = vec![vec![0.0; width]; height];
-
-    for y in 0..height {
-        for x in 0..width {
-            let idx = (y * width + x) * 4;
-            let mut lum = rgb_to_luminance(data[idx], data[idx + 1], data[idx + 2]);
-            if invert {
-                lum = 255.0 - lum;
+    let data = &buffers.composited;
+    buffers.colors.clear();
+    buffers.backgrounds.clear();
+    if options.color_format == ColorFormat::Plain {
+        // Plain text needs no colors
+    } else if options.render_mode == RenderMode::HalfBlock {
+        // In color, every half-block cell carries two pixels as fg/bg,
+        // except cells whose pixels are both blank
+        for (i, cell) in buffers.cells.iter_mut().enumerate() {
+            let (x, top) = (i % width, i / width * 2);
+            let bottom = (top + 1).min(height - 1);
+            let blank = &buffers.blank;
+            if blank.is_empty() || !(blank[top * width + x] && blank[bottom * width + x]) {
+                *cell = '▀';
             }
-            lum = clamp(
-                contrast_factor * (lum - 128.0) + 128.0 + brightness,
-                0.0,
-                255.0,
-            );
-            gray_2d[y][x] = lum;
         }
+        half_block_colors(data, width, height, &mut buffers.colors, &mut buffers.backgrounds);
+    } else {
+        average_cell_colors(data, width, height, grid.cell_width, grid.cell_height, &mut buffers.colors);
     }
+    render_colored(&buffers.cells, &buffers.colors, &buffers.backgrounds, grid.cols, grid.rows, options.color_format)
+}
 
-    // Apply DoG
-    let sigma1 = 0.5;
-    let sigma2 = 1.0;
-    let kernel_size = 3;
-    let dog = difference_of_gaussians_2d(&gray_2d, sigma1, sigma2, kernel_size);
-
-    // Apply Sobel
-    let (mag, angle) = apply_sobel_2d(&dog, width, height);
-
-    // Non-maximum suppression
-    let suppressed_mag = non_max_suppression(&mag, &angle, width, height);
-
-    // Generate ASCII
-    let mut ascii = String::new();
-    for y in 0..height {
-        for x in 0..width {
-            if suppressed_mag[y][x] > threshold {
-                let adjusted_angle = (angle[y][x] + 90.0) % 180.0;
-                let edge_char = if adjusted_angle < 22.5 || adjusted_angle >= 157.5 {
-                    "-"
-                } else if adjusted_angle < 67.5 {
-                    "/"
-                } else if adjusted_angle < 112.5 {
-                    "|"
-                } else {
-                    "\\"
-                };
-                ascii.push_str(edge_char);
-            } else {
-                ascii.push(' ');
-            }
-        }
-        ascii.push('\n');
-    }
+// Build options from the string arguments of the legacy entry points
+#[allow(clippy::too_many_arguments)]
+fn legacy_options(
+    invert: bool,
+    charset: &str,
+    manual_char: &str,
+    ignore_white: bool,
+    dithering: bool,
+    dither_algorithm: &str,
+    edge_method: &str,
+    edge_threshold: f64,
+    dog_threshold: f64,
+    brightness: f64,
+    contrast: f64,
+) -> Result<ConvertOptions, String> {
+    let chars = if charset == "manual" {
+        manual_char
+    } else {
+        charset
+    };
+    let dither_algorithm = if dithering {
+        DitherAlgorithm::from_str(dither_algorithm)?
+    } else {
+        DitherAlgorithm::None
+    };
+
+    Ok(ConvertOptions::new()
+        .with_charset(chars)
+        .with_invert(invert)
+        .with_ignore_white(ignore_white)
+        .with_dither_algorithm(dither_algorithm)
+        .with_edge_method(EdgeMethod::from_str(edge_method)?)
+        .with_edge_threshold(edge_threshold)
+        .with_dog_threshold(dog_threshold)
+        .with_brightness(brightness)
+        .with_contrast(contrast))
+}
 
-    ascii
+/// Convert an RGBA image to ASCII art
+///
+/// Returns an error instead of trapping when the options do not fit the
+/// input (empty charset, buffer size not matching `width * height * 4`, ...).
+/// Transparent pixels are composited over the options' background color
+/// first. The result is colored according to the options' `ColorFormat`.
+#[wasm_bindgen]
+pub fn convert_with_options(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    options: &ConvertOptions,
+) -> Result<String, String> {
+    options.validate(data.len(), width, height)?;
+
+    let mut buffers = FrameBuffers::default();
+    composite_over(data, options.background, &mut buffers.composited);
+    let grid = convert_to_grid(width, height, options, &mut buffers);
+    Ok(render_grid(width, height, &grid, options, &mut buffers))
 }
 
 // Main conversion function
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn convert_to_ascii(
     data: &[u8],
     width: usize,
@@ -449,72 +440,147 @@ pub fn convert_to_ascii(
     dog_threshold: f64,
     brightness: f64,
     contrast: f64,
-) -> String {
-    // Special handling for DoG contour mode
-    if edge_method == "dog" {
-        return generate_contour_ascii(data, width, height, invert, brightness, contrast, dog_threshold);
+) -> Result<String, String> {
+    let options = legacy_options(
+        invert, charset, manual_char, ignore_white, dithering, dither_algorithm,
+        edge_method, edge_threshold, dog_threshold, brightness, contrast,
+    )?;
+    convert_with_options(data, width, height, &options)
+}
+
+/// Convert an image to colored ASCII art
+///
+/// Characters are chosen exactly as in `convert_to_ascii`; each cell is
+/// additionally tinted with the average source color it covers.
+///
+/// # Arguments
+/// * `color_format` - "ansi" (truecolor), "ansi256", "html" or "plain"
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn convert_to_ascii_colored(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    invert: bool,
+    charset: &str,
+    manual_char: &str,
+    ignore_white: bool,
+    dithering: bool,
+    dither_algorithm: &str,
+    edge_method: &str,
+    edge_threshold: f64,
+    dog_threshold: f64,
+    brightness: f64,
+    contrast: f64,
+    color_format: &str,
+) -> Result<String, String> {
+    let options = legacy_options(
+        invert, charset, manual_char, ignore_white, dithering, dither_algorithm,
+        edge_method, edge_threshold, dog_threshold, brightness, contrast,
+    )?
+    .with_color_format(ColorFormat::from_str(color_format)?);
+    convert_with_options(data, width, height, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Left half black, right half white
+    fn split_image(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| if i % width < width / 2 { [0, 0, 0, 255] } else { [255; 4] })
+            .collect()
     }
 
-    // Convert to grayscale and apply brightness/contrast
-    let contrast_factor = (259.0 * (contrast + 255.0)) / (255.0 * (259.0 - contrast));
-    let mut gray = Vec::with_capacity(width * height);
-    let mut gray_original = Vec::with_capacity(width * height);
+    #[test]
+    fn test_convert_with_options_reports_size_mismatch() {
+        let options = ConvertOptions::new();
+        let err = convert_with_options(&[0; 12], 2, 2, &options).unwrap_err();
+        assert!(err.contains("expected 16"));
+    }
 
-    for i in (0..data.len()).step_by(4) {
-        let mut lum = rgb_to_luminance(data[i], data[i + 1], data[i + 2]);
-        if invert {
-            lum = 255.0 - lum;
-        }
-        let adjusted = clamp(
-            contrast_factor * (lum - 128.0) + 128.0 + brightness,
-            0.0,
-            255.0,
-        );
-        gray.push(adjusted);
-        gray_original.push(adjusted);
+    #[test]
+    fn test_hybrid_overlays_edge_glyphs_on_fill() {
+        let (width, height) = (8, 9);
+        let data = split_image(width, height);
+        let options = ConvertOptions::new()
+            .with_charset("@ ")
+            .with_edge_method(EdgeMethod::Hybrid)
+            .with_dog_threshold(10.0);
+        let ascii = convert_with_options(&data, width, height, &options).unwrap();
+        let middle = ascii.lines().nth(4).unwrap();
+
+        assert!(middle.starts_with('@'));
+        assert!(middle.contains('|'));
+        assert!(middle.chars().all(|c| c == '@' || c == '|' || c == ' '));
     }
 
-    // Apply Sobel edge detection if enabled
-    if edge_method == "sobel" {
-        gray = apply_sobel_edge_detection(&gray, width, height, edge_threshold);
+    #[test]
+    fn test_half_blocks_use_two_colors_per_cell() {
+        // White row over a black row
+        let data = [255, 255, 255, 255, 0, 0, 0, 255];
+        let options = ConvertOptions::new().with_render_mode(RenderMode::HalfBlock);
+        assert_eq!(convert_with_options(&data, 1, 2, &options).unwrap(), "▀\n");
+
+        let colored = options.with_color_format(ColorFormat::AnsiTrueColor);
+        assert_eq!(
+            convert_with_options(&data, 1, 2, &colored).unwrap(),
+            "\x1b[38;2;255;255;255;48;2;0;0;0m▀\x1b[0m\n"
+        );
     }
 
-    // Get character set
-    let chars = if charset == "manual" {
-        manual_char
-    } else {
-        charset
-    };
-    let char_array: Vec<char> = chars.chars().collect();
-    let n_levels = char_array.len();
+    #[test]
+    fn test_half_blocks_keep_transparent_cells_blank() {
+        // Left column white over black, right column fully transparent
+        let data = [255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0];
+        let options = ConvertOptions::new()
+            .with_render_mode(RenderMode::HalfBlock)
+            .with_alpha_threshold(128)
+            .with_color_format(ColorFormat::AnsiTrueColor);
+        assert_eq!(
+            convert_with_options(&data, 2, 2, &options).unwrap(),
+            "\x1b[38;2;255;255;255;48;2;0;0;0m▀\x1b[0m \n"
+        );
+    }
 
-    // Apply dithering if enabled
-    if dithering && edge_method != "sobel" {
-        gray = match dither_algorithm {
-            "floyd" => apply_floyd_steinberg_dithering(&gray, width, height, n_levels),
-            "atkinson" => apply_atkinson_dithering(&gray, width, height, n_levels),
-            "noise" => apply_noise_dithering(&gray, width, height, n_levels),
-            "ordered" => apply_ordered_dithering(&gray, width, height, n_levels),
-            _ => gray,
-        };
+    #[test]
+    fn test_ignore_white_checks_adjusted_brightness() {
+        // Light gray only becomes pure white once brightened
+        let data = [230, 230, 230, 255, 0, 0, 0, 255];
+        let options = ConvertOptions::new().with_charset(".#").with_ignore_white(true);
+        assert_eq!(convert_with_options(&data, 2, 1, &options).unwrap(), "#.\n");
+        let brighter = options.clone().with_brightness(40.0);
+        assert_eq!(convert_with_options(&data, 2, 1, &brighter).unwrap(), " .\n");
+        // Unaffected by the compositing background
+        let dark = brighter.with_background(0, 0, 0);
+        assert_eq!(convert_with_options(&data, 2, 1, &dark).unwrap(), " .\n");
     }
 
-    // Convert to ASCII
-    let mut ascii = String::new();
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            if ignore_white && gray_original[idx] == 255.0 {
-                ascii.push(' ');
-                continue;
-            }
-            let computed_level = ((gray[idx] / 255.0) * (n_levels - 1) as f64).round() as usize;
-            let char_idx = computed_level.min(n_levels - 1);
-            ascii.push(char_array[char_idx]);
-        }
-        ascii.push('\n');
+    #[test]
+    fn test_transparent_pixels_composite_and_blank() {
+        // Opaque black, half-transparent black and fully transparent pixels
+        let data = [0, 0, 0, 255, 0, 0, 0, 128, 0, 0, 0, 0];
+        let options = ConvertOptions::new().with_charset(" .:#");
+        assert_eq!(convert_with_options(&data, 3, 1, &options).unwrap(), " .#\n");
+        let options = options.with_alpha_threshold(100);
+        assert_eq!(convert_with_options(&data, 3, 1, &options).unwrap(), " . \n");
+        let options = options.with_background(0, 0, 0).with_alpha_threshold(0);
+        assert_eq!(convert_with_options(&data, 3, 1, &options).unwrap(), "   \n");
     }
 
-    ascii
+    #[test]
+    fn test_glyph_match_emits_one_char_per_cell() {
+        let (width, height) = (13, 20);
+        let data = split_image(width, height);
+        let options = ConvertOptions::new()
+            .with_render_mode(RenderMode::GlyphMatch)
+            .with_glyph_match(GlyphMetric::Mse, 6, 10);
+        let ascii = convert_with_options(&data, width, height, &options).unwrap();
+        let lines: Vec<&str> = ascii.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.chars().count() == 3));
+        assert!(lines[0].starts_with(' '));
+    }
 }
-
@@ -0,0 +1,96 @@
+use crate::rng::Rng;
+
+/// Seeded 2D Perlin gradient noise
+pub(crate) struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    pub(crate) fn new(seed: u32) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        let mut rng = Rng::new(seed as u64);
+        for i in (1..table.len()).rev() {
+            table.swap(i, rng.below(i + 1));
+        }
+        let mut perm = [0; 512];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = table[i & 255];
+        }
+        Perlin { perm }
+    }
+
+    /// Noise at (x, y), roughly in [-1, 1] and 0 at integer coordinates
+    pub(crate) fn noise(&self, x: f64, y: f64) -> f64 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let xi = (x0 as i64 & 255) as usize;
+        let yi = (y0 as i64 & 255) as usize;
+
+        let hash = |dx: usize, dy: usize| self.perm[self.perm[xi + dx] as usize + yi + dy];
+        let u = fade(fx);
+        let v = fade(fy);
+        let bottom = lerp(grad(hash(0, 0), fx, fy), grad(hash(1, 0), fx - 1.0, fy), u);
+        let top = lerp(grad(hash(0, 1), fx, fy - 1.0), grad(hash(1, 1), fx - 1.0, fy - 1.0), u);
+        lerp(bottom, top, v)
+    }
+
+    /// Sum of `octaves` noise layers, each twice the frequency and half the
+    /// amplitude of the last, scaled back to roughly [-1, 1]
+    pub(crate) fn fbm(&self, x: f64, y: f64, octaves: u32) -> f64 {
+        let (mut sum, mut total, mut amplitude, mut frequency) = (0.0, 0.0, 1.0, 1.0);
+        for _ in 0..octaves {
+            sum += amplitude * self.noise(x * frequency, y * frequency);
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        if total > 0.0 {
+            sum / total
+        } else {
+            0.0
+        }
+    }
+}
+
+// Quintic smoothstep, flat at 0 and 1 so cells join without creases
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+// Dot product of the offset with one of eight gradient directions
+fn grad(hash: u8, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_is_smooth_bounded_and_seeded() {
+        let a = Perlin::new(3);
+        let b = Perlin::new(4);
+        assert_eq!(a.noise(2.0, 5.0), 0.0);
+        let samples: Vec<f64> = (0..500).map(|i| a.noise(i as f64 * 0.37, i as f64 * 0.11)).collect();
+        assert!(samples.iter().all(|v| v.abs() <= 1.0));
+        assert!(samples.iter().any(|v| v.abs() > 0.2));
+        assert_eq!(samples[17], Perlin::new(3).noise(17.0 * 0.37, 17.0 * 0.11));
+        let other: Vec<f64> = (0..500).map(|i| b.noise(i as f64 * 0.37, i as f64 * 0.11)).collect();
+        assert_ne!(samples, other);
+        // Neighboring samples stay close
+        assert!((a.noise(1.3, 1.3) - a.noise(1.31, 1.3)).abs() < 0.05);
+    }
+}
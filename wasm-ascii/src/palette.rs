@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Most colors an indexed PNG can hold
+pub(crate) const MAX_PALETTE_COLORS: usize = 256;
+
+/// Image reduced to a palette of RGBA colors and one index per pixel
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IndexedImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) palette: Vec<[u8; 4]>,
+    pub(crate) indices: Vec<u8>,
+}
+
+/// Exact palette of RGBA pixels, or `None` if they use more than
+/// `max_colors` colors
+pub(crate) fn exact_palette(rgba: &[u8], width: u32, height: u32, max_colors: usize) -> Option<IndexedImage> {
+    let max_colors = max_colors.min(MAX_PALETTE_COLORS);
+    let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+
+    for pixel in rgba.chunks_exact(4) {
+        let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        let index = match lookup.get(&color) {
+            Some(&index) => index,
+            None => {
+                if palette.len() == max_colors {
+                    return None;
+                }
+                let index = palette.len() as u8;
+                palette.push(color);
+                lookup.insert(color, index);
+                index
+            }
+        };
+        indices.push(index);
+    }
+
+    Some(IndexedImage { width, height, palette, indices })
+}
+
+/// Reduce RGBA pixels to at most `max_colors` colors with median cut
+///
+/// The color histogram is split repeatedly at the weighted median of the
+/// box with the widest channel range; each box becomes the average of its
+/// pixels, and every pixel maps to the nearest palette entry.
+pub(crate) fn median_cut(rgba: &[u8], width: u32, height: u32, max_colors: usize) -> IndexedImage {
+    let max_colors = max_colors.clamp(1, MAX_PALETTE_COLORS);
+
+    // Ordered, so equal inputs always split into the same boxes
+    let mut histogram: BTreeMap<[u8; 4], u32> = BTreeMap::new();
+    for pixel in rgba.chunks_exact(4) {
+        *histogram.entry([pixel[0], pixel[1], pixel[2], pixel[3]]).or_insert(0) += 1;
+    }
+    let colors: Vec<([u8; 4], u32)> = histogram.into_iter().collect();
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // Box with the widest single channel; boxes of one color can't split
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        // Ties on the channel break on the whole color for a stable split
+        colors.sort_unstable_by_key(|&(color, _)| (color[channel], color));
+        let total: u64 = colors.iter().map(|&(_, count)| count as u64).sum();
+        let mut seen = 0;
+        let mut split = 1;
+        for (i, &(_, count)) in colors.iter().enumerate() {
+            seen += count as u64;
+            if seen * 2 >= total {
+                split = (i + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let palette: Vec<[u8; 4]> = boxes.iter().map(|colors| average(colors)).collect();
+
+    let mut nearest: HashMap<[u8; 4], u8> = HashMap::new();
+    let indices = rgba
+        .chunks_exact(4)
+        .map(|pixel| {
+            let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            *nearest.entry(color).or_insert_with(|| nearest_index(&palette, color))
+        })
+        .collect();
+
+    IndexedImage { width, height, palette, indices }
+}
+
+// Channel with the largest spread in `colors` and that spread
+fn widest_channel(colors: &[([u8; 4], u32)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((u8::MAX, u8::MIN), |(min, max), (color, _)| {
+                (min.min(color[channel]), max.max(color[channel]))
+            });
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+// Pixel-count weighted average color
+fn average(colors: &[([u8; 4], u32)]) -> [u8; 4] {
+    let mut sums = [0u64; 4];
+    let mut total = 0u64;
+    for &(color, count) in colors {
+        for (sum, &channel) in sums.iter_mut().zip(&color) {
+            *sum += channel as u64 * count as u64;
+        }
+        total += count as u64;
+    }
+    sums.map(|sum| ((sum + total / 2) / total.max(1)) as u8)
+}
+
+fn nearest_index(palette: &[[u8; 4]], color: [u8; 4]) -> u8 {
+    let distance = |entry: &[u8; 4]| -> u32 {
+        entry
+            .iter()
+            .zip(&color)
+            .map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum()
+    };
+    (0..palette.len()).min_by_key(|&i| distance(&palette[i])).unwrap_or(0) as u8
+}
+
+/// Encode as an indexed PNG at the smallest bit depth (1, 2, 4 or 8) that
+/// holds the palette
+pub(crate) fn encode_indexed_png(image: &IndexedImage) -> Result<Vec<u8>, String> {
+    let bit_depth = match image.palette.len() {
+        0..=2 => png::BitDepth::One,
+        3..=4 => png::BitDepth::Two,
+        5..=16 => png::BitDepth::Four,
+        _ => png::BitDepth::Eight,
+    };
+    let bits = bit_depth as usize;
+
+    // Rows start on a byte boundary, pixels packed from the high bits down
+    let width = image.width as usize;
+    let row_bytes = (width * bits).div_ceil(8);
+    let mut packed = vec![0u8; row_bytes * image.height as usize];
+    if width > 0 {
+        for (row, indices) in packed.chunks_mut(row_bytes).zip(image.indices.chunks(width)) {
+            for (x, &index) in indices.iter().enumerate() {
+                let bit = x * bits;
+                row[bit / 8] |= index << (8 - bits - bit % 8);
+            }
+        }
+    }
+
+    let palette: Vec<u8> = image.palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+    // Alpha per entry, omitting the opaque tail
+    let mut alpha: Vec<u8> = image.palette.iter().map(|c| c[3]).collect();
+    while alpha.last() == Some(&255) {
+        alpha.pop();
+    }
+
+    let mut output = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut output, image.width, image.height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(bit_depth);
+        encoder.set_palette(palette);
+        if !alpha.is_empty() {
+            encoder.set_trns(alpha);
+        }
+        encoder.set_compression(png::Compression::High);
+        // Palette indices aren't smooth, so prediction filters rarely help
+        encoder.set_filter(png::Filter::NoFilter);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+        writer
+            .write_image_data(&packed)
+            .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+        writer.finish().map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixels(colors: &[[u8; 4]]) -> Vec<u8> {
+        colors.iter().flatten().copied().collect()
+    }
+
+    #[test]
+    fn test_exact_palette_round_trips_at_low_bit_depth() {
+        let (ink, paper, clear) = ([0, 0, 0, 255], [255, 255, 255, 255], [0, 0, 0, 0]);
+        let rgba = pixels(&[ink, paper, paper, clear, ink, ink, paper, clear, clear]);
+        let indexed = exact_palette(&rgba, 3, 3, 256).unwrap();
+        assert_eq!(indexed.palette, vec![ink, paper, clear]);
+        assert!(exact_palette(&rgba, 3, 3, 2).is_none());
+
+        let png = encode_indexed_png(&indexed).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(&png));
+        let info = decoder.read_info().unwrap().info().clone();
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        assert_eq!(info.bit_depth, png::BitDepth::Two);
+
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded.as_raw(), &rgba);
+    }
+
+    #[test]
+    fn test_median_cut_finds_color_clusters() {
+        // Two clusters of near-identical reds and blues
+        let mut colors = Vec::new();
+        for i in 0..20u8 {
+            colors.push([200 + i, 10, 10, 255]);
+            colors.push([10, 10, 200 + i, 255]);
+        }
+        let indexed = median_cut(&pixels(&colors), 40, 1, 2);
+        assert_eq!(indexed.palette.len(), 2);
+        assert!(indexed.palette.contains(&[210, 10, 10, 255]));
+        assert!(indexed.palette.contains(&[10, 10, 210, 255]));
+        // Each pixel maps to its own cluster
+        assert_ne!(indexed.indices[0], indexed.indices[1]);
+        assert!(indexed.indices.chunks(2).all(|pair| pair == indexed.indices[..2].to_vec()));
+    }
+
+    #[test]
+    fn test_median_cut_encodes_identically_every_time() {
+        // More colors than fit a palette, with many ties on each channel
+        let colors: Vec<[u8; 4]> = (0..48u32 * 48)
+            .map(|i| [(i % 48 * 5) as u8, (i / 48 * 5) as u8, ((i % 7) * 36) as u8, 255])
+            .collect();
+        let rgba = pixels(&colors);
+        let encode = || encode_indexed_png(&median_cut(&rgba, 48, 48, MAX_PALETTE_COLORS)).unwrap();
+        assert_eq!(encode(), encode());
+    }
+}
@@ -0,0 +1,402 @@
+use wasm_bindgen::prelude::*;
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use std::fmt::Write;
+
+use crate::font::{cell_mask, CELL_HEIGHT, CELL_WIDTH};
+use crate::frame_size::check_frame_size;
+use crate::subcell::{BRAILLE_DOTS, QUADRANTS};
+
+type CellMask = [bool; CELL_WIDTH * CELL_HEIGHT];
+
+/// Options for `render_ascii_to_png` and `render_ascii_to_svg`
+///
+/// Colors and geometry of the rendered image. The same options give the
+/// same PNG and SVG layout, so a preview can be swapped for a download.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    foreground: [u8; 3],
+    background: [u8; 3],
+    cell_colors: Vec<u8>,
+    padding: usize,
+    scale: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            foreground: [255, 255, 255],
+            background: [0, 0, 0],
+            cell_colors: Vec::new(),
+            padding: 0,
+            scale: 1,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderOptions {
+    /// White text on black, no padding, one image pixel per font pixel
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RenderOptions {
+        Self::default()
+    }
+
+    pub fn with_foreground(mut self, r: u8, g: u8, b: u8) -> RenderOptions {
+        self.foreground = [r, g, b];
+        self
+    }
+
+    pub fn with_background(mut self, r: u8, g: u8, b: u8) -> RenderOptions {
+        self.background = [r, g, b];
+        self
+    }
+
+    /// Per-cell text colors as RGB triples, row-major
+    ///
+    /// The grid is as wide as the longest line; cells past the end of a
+    /// shorter line are skipped. An empty buffer uses the foreground color.
+    pub fn with_cell_colors(mut self, colors: Vec<u8>) -> RenderOptions {
+        self.cell_colors = colors;
+        self
+    }
+
+    /// Blank border around the text, in image pixels
+    pub fn with_padding(mut self, padding: usize) -> RenderOptions {
+        self.padding = padding;
+        self
+    }
+
+    /// Image pixels per font pixel; a character cell is 6x10 font pixels
+    pub fn with_scale(mut self, scale: usize) -> RenderOptions {
+        self.scale = scale;
+        self
+    }
+}
+
+// Text split into a character grid, with the rendered image size
+struct Layout {
+    lines: Vec<Vec<char>>,
+    cols: usize,
+    image_width: usize,
+    image_height: usize,
+}
+
+impl Layout {
+    fn new(text: &str, options: &RenderOptions) -> Result<Self, String> {
+        if options.scale == 0 {
+            return Err("Scale must be at least 1".to_string());
+        }
+
+        let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+        let cols = lines.iter().map(Vec::len).max().unwrap_or(0);
+        let rows = lines.len();
+
+        let side = |cells: usize, cell_size: usize| {
+            cells
+                .checked_mul(cell_size)
+                .and_then(|size| size.checked_mul(options.scale))
+                .and_then(|pixels| pixels.checked_add(options.padding.checked_mul(2)?))
+        };
+        let (Some(image_width), Some(image_height)) = (side(cols, CELL_WIDTH), side(rows, CELL_HEIGHT)) else {
+            return Err(format!(
+                "Rendered image of {}x{} cells at scale {} with padding {} is too large",
+                cols, rows, options.scale, options.padding
+            ));
+        };
+        check_frame_size("Rendered image", "pixels", image_width, image_height)?;
+
+        if !options.cell_colors.is_empty() && options.cell_colors.len() != cols * rows * 3 {
+            return Err(format!(
+                "Cell colors have {} bytes, expected {} for {}x{} cells",
+                options.cell_colors.len(),
+                cols * rows * 3,
+                cols,
+                rows
+            ));
+        }
+
+        Ok(Layout { lines, cols, image_width, image_height })
+    }
+
+    // Call `f(x, y, len, color)` for every horizontal run of inked font
+    // pixels, in font pixel coordinates
+    fn for_each_run(&self, options: &RenderOptions, mut f: impl FnMut(usize, usize, usize, [u8; 3])) {
+        for (row, line) in self.lines.iter().enumerate() {
+            for (col, &c) in line.iter().enumerate() {
+                let Some(mask) = glyph_mask(c) else {
+                    continue;
+                };
+                let idx = (row * self.cols + col) * 3;
+                let color = match options.cell_colors.get(idx..idx + 3) {
+                    Some(rgb) => [rgb[0], rgb[1], rgb[2]],
+                    None => options.foreground,
+                };
+
+                for y in 0..CELL_HEIGHT {
+                    let mut x = 0;
+                    while x < CELL_WIDTH {
+                        if !mask[y * CELL_WIDTH + x] {
+                            x += 1;
+                            continue;
+                        }
+                        let start = x;
+                        while x < CELL_WIDTH && mask[y * CELL_WIDTH + x] {
+                            x += 1;
+                        }
+                        f(col * CELL_WIDTH + start, row * CELL_HEIGHT + y, x - start, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Block element, braille or sextant glyph drawn from geometry instead of
+// the bitmap font
+enum Shape {
+    // Font rows `start..end` or columns `start..end` fully inked
+    Rows(usize, usize),
+    Columns(usize, usize),
+    // Light, medium and dark shades as 25%, 50% and 75% patterns
+    LightShade,
+    MediumShade,
+    DarkShade,
+    // Inked quadrants, braille dots and sextants as in `subcell`'s numbering
+    Quadrants(usize),
+    Braille(u32),
+    Sextants(u32),
+}
+
+impl Shape {
+    fn of(c: char) -> Option<Shape> {
+        let code = c as u32;
+        Some(match code {
+            // Upper half, lower eighths, full block, left eighths
+            0x2580 => Shape::Rows(0, CELL_HEIGHT / 2),
+            0x2581..=0x2588 => Shape::Rows(CELL_HEIGHT - eighths(code - 0x2580, CELL_HEIGHT), CELL_HEIGHT),
+            0x2589..=0x258F => Shape::Columns(0, eighths(0x2590 - code, CELL_WIDTH)),
+            0x2590 => Shape::Columns(CELL_WIDTH / 2, CELL_WIDTH),
+            0x2591 => Shape::LightShade,
+            0x2592 => Shape::MediumShade,
+            0x2593 => Shape::DarkShade,
+            0x2594 => Shape::Rows(0, eighths(1, CELL_HEIGHT)),
+            0x2595 => Shape::Columns(CELL_WIDTH - eighths(1, CELL_WIDTH), CELL_WIDTH),
+            0x2596..=0x259F => Shape::Quadrants(QUADRANTS.iter().position(|&q| q == c)?),
+            0x2800..=0x28FF => Shape::Braille(code - 0x2800),
+            0x1FB00..=0x1FB3B => {
+                // Inverse of the sextant numbering, which skips the half blocks
+                let mut bits = code - 0x1FB00 + 1;
+                if bits >= 21 {
+                    bits += 1;
+                }
+                if bits >= 42 {
+                    bits += 1;
+                }
+                Shape::Sextants(bits)
+            }
+            _ => return None,
+        })
+    }
+
+    fn inked(&self, x: usize, y: usize) -> bool {
+        match *self {
+            Shape::Rows(start, end) => (start..end).contains(&y),
+            Shape::Columns(start, end) => (start..end).contains(&x),
+            Shape::LightShade => (x + 2 * y).is_multiple_of(4),
+            Shape::MediumShade => (x + y).is_multiple_of(2),
+            Shape::DarkShade => !(x + 2 * y).is_multiple_of(4),
+            Shape::Quadrants(bits) => {
+                let bit = (y >= CELL_HEIGHT / 2) as usize * 2 + (x >= CELL_WIDTH / 2) as usize;
+                bits & (1 << bit) != 0
+            }
+            Shape::Braille(dots) => {
+                let col = match x {
+                    1 => 0,
+                    4 => 1,
+                    _ => return false,
+                };
+                y % 2 == 1 && y < 8 && dots & BRAILLE_DOTS[col][y / 2] != 0
+            }
+            Shape::Sextants(bits) => {
+                let bit = (y * 3 / CELL_HEIGHT) * 2 + (x >= CELL_WIDTH / 2) as usize;
+                bits & (1 << bit) != 0
+            }
+        }
+    }
+}
+
+// Mask for any character the converter can emit: printable ASCII from the
+// embedded font, block elements, braille and sextants drawn geometrically.
+// Spaces draw nothing and any other character draws as '?'.
+fn glyph_mask(c: char) -> Option<CellMask> {
+    if c == ' ' {
+        return None;
+    }
+    if let Some(mask) = cell_mask(c) {
+        return Some(mask);
+    }
+    let Some(shape) = Shape::of(c) else {
+        return cell_mask('?');
+    };
+
+    let mut mask = [false; CELL_WIDTH * CELL_HEIGHT];
+    for (i, pixel) in mask.iter_mut().enumerate() {
+        *pixel = shape.inked(i % CELL_WIDTH, i / CELL_WIDTH);
+    }
+    Some(mask)
+}
+
+// `n` eighths of `size` pixels, rounded
+fn eighths(n: u32, size: usize) -> usize {
+    (n as usize * size + 4) / 8
+}
+
+/// Render ASCII art to a PNG image with the embedded bitmap font
+///
+/// Each character occupies a 6x10 cell scaled by the options' `scale`.
+/// Output is deterministic: the same text and options always produce the
+/// same bytes.
+///
+/// # Arguments
+/// * `text` - ASCII art, one line per row (e.g. from `convert_to_ascii`)
+/// * `options` - Colors, padding and scale
+///
+/// # Returns
+/// PNG file bytes
+#[wasm_bindgen]
+pub fn render_ascii_to_png(text: &str, options: &RenderOptions) -> Result<Vec<u8>, String> {
+    let (pixels, width, height) = render_ascii_to_rgb(text, options)?;
+    let mut output = Vec::new();
+    PngEncoder::new(&mut output)
+        .write_image(&pixels, width as u32, height as u32, ExtendedColorType::Rgb8)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(output)
+}
+
+/// Render ASCII art to an RGB pixel buffer, returned with its width and height
+pub(crate) fn render_ascii_to_rgb(text: &str, options: &RenderOptions) -> Result<(Vec<u8>, usize, usize), String> {
+    let layout = Layout::new(text, options)?;
+    let (width, height, scale, padding) = (layout.image_width, layout.image_height, options.scale, options.padding);
+
+    let mut pixels = options.background.repeat(width * height);
+    layout.for_each_run(options, |x, y, len, color| {
+        let x0 = padding + x * scale;
+        for py in padding + y * scale..padding + (y + 1) * scale {
+            for px in x0..x0 + len * scale {
+                let idx = (py * width + px) * 3;
+                pixels[idx..idx + 3].copy_from_slice(&color);
+            }
+        }
+    });
+
+    Ok((pixels, width, height))
+}
+
+/// Render ASCII art to an SVG document with the embedded bitmap font
+///
+/// Glyphs are drawn as pixel rectangles, one path per color, so the image
+/// looks the same as `render_ascii_to_png` without depending on installed
+/// fonts.
+///
+/// # Arguments
+/// * `text` - ASCII art, one line per row (e.g. from `convert_to_ascii`)
+/// * `options` - Colors, padding and scale
+///
+/// # Returns
+/// SVG markup
+#[wasm_bindgen]
+pub fn render_ascii_to_svg(text: &str, options: &RenderOptions) -> Result<String, String> {
+    let layout = Layout::new(text, options)?;
+    let (scale, padding) = (options.scale, options.padding);
+
+    // Paths in order of first use so the output is deterministic
+    let mut paths: Vec<([u8; 3], String)> = Vec::new();
+    layout.for_each_run(options, |x, y, len, color| {
+        let index = match paths.iter().position(|(c, _)| *c == color) {
+            Some(index) => index,
+            None => {
+                paths.push((color, String::new()));
+                paths.len() - 1
+            }
+        };
+        let _ = write!(
+            paths[index].1,
+            "M{} {}h{}v{}h-{}z",
+            padding + x * scale,
+            padding + y * scale,
+            len * scale,
+            scale,
+            len * scale
+        );
+    });
+
+    let [r, g, b] = options.background;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"100%\" height=\"100%\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+        r,
+        g,
+        b,
+        w = layout.image_width,
+        h = layout.image_height,
+    );
+    for ([r, g, b], d) in paths {
+        let _ = write!(svg, "<path fill=\"#{:02x}{:02x}{:02x}\" d=\"{}\"/>", r, g, b, d);
+    }
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(png: &[u8]) -> image::RgbImage {
+        image::load_from_memory(png).unwrap().to_rgb8()
+    }
+
+    #[test]
+    fn test_png_layout_padding_and_scale() {
+        let options = RenderOptions::new().with_padding(3).with_scale(2);
+        let png = render_ascii_to_png("I\n##\n", &options).unwrap();
+        assert_eq!(png, render_ascii_to_png("I\n##\n", &options).unwrap());
+
+        let img = decode(&png);
+        assert_eq!(img.dimensions(), (2 * 6 * 2 + 6, 2 * 10 * 2 + 6));
+        // Top bar of 'I' starts one font pixel below the cell top
+        assert_eq!(img.get_pixel(3, 3).0, [0, 0, 0]);
+        assert_eq!(img.get_pixel(3 + 2, 3 + 2).0, [255, 255, 255]);
+        // Past the end of the short first line stays background
+        assert!((3 + 12..3 + 24).all(|x| img.get_pixel(x, 3 + 2).0 == [0, 0, 0]));
+
+        // 4200x7000 pixels: each side is modest, the total is not
+        let err = render_ascii_to_png("#", &RenderOptions::new().with_scale(700)).unwrap_err();
+        assert!(err.contains("got 4200x7000"), "{}", err);
+    }
+
+    #[test]
+    fn test_cell_colors_and_block_glyphs() {
+        let options = RenderOptions::new()
+            .with_background(0, 0, 255)
+            .with_cell_colors(vec![255, 0, 0, 0, 255, 0]);
+        let img = decode(&render_ascii_to_png("█▄", &options).unwrap());
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(img.get_pixel(6, 0).0, [0, 0, 255]);
+        assert_eq!(img.get_pixel(6, 9).0, [0, 255, 0]);
+
+        let err = render_ascii_to_png("ab", &RenderOptions::new().with_cell_colors(vec![1, 2, 3])).unwrap_err();
+        assert!(err.contains("expected 6"), "{}", err);
+    }
+
+    #[test]
+    fn test_svg_groups_runs_by_color() {
+        let svg = render_ascii_to_svg("-", &RenderOptions::new().with_scale(2)).unwrap();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert!(svg.contains("width=\"12\" height=\"20\""));
+        // '-' is a single 5 pixel run on font row 4
+        assert!(svg.contains("<path fill=\"#ffffff\" d=\"M0 8h10v2h-10z\"/>"), "{}", svg);
+        assert!(render_ascii_to_svg("", &RenderOptions::new()).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use wasm_bindgen::prelude::*;
+
+use crate::frame_size::check_frame_size;
+
+// Classic donut shades from darkest to brightest
+const DEFAULT_RAMP: &str = ".,-~:;=!*#$@";
+
+// Ray marching limits
+const MAX_STEPS: usize = 128;
+const HIT_DISTANCE: f64 = 1e-3;
+// Distance from the camera to the image plane; about a 67 degree view
+const FOCAL_LENGTH: f64 = 1.5;
+// Terminal cells are about twice as tall as wide
+const CELL_ASPECT: f64 = 0.5;
+const AMBIENT: f64 = 0.08;
+
+type Vec3 = [f64; 3];
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Vec3, s: f64) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    scale(a, 1.0 / length(a))
+}
+
+/// Signed-distance primitive, positioned in scene coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Primitive {
+    Sphere { center: Vec3, radius: f64 },
+    /// Ring around the y axis
+    Torus { center: Vec3, major: f64, minor: f64 },
+    Box { center: Vec3, half_size: Vec3 },
+}
+
+impl Primitive {
+    fn distance(&self, p: Vec3) -> f64 {
+        match *self {
+            Primitive::Sphere { center, radius } => length(sub(p, center)) - radius,
+            Primitive::Torus { center, major, minor } => {
+                let q = sub(p, center);
+                let ring = (q[0] * q[0] + q[2] * q[2]).sqrt() - major;
+                (ring * ring + q[1] * q[1]).sqrt() - minor
+            }
+            Primitive::Box { center, half_size } => {
+                let q = sub(p, center);
+                let d = [q[0].abs() - half_size[0], q[1].abs() - half_size[1], q[2].abs() - half_size[2]];
+                let outside = length([d[0].max(0.0), d[1].max(0.0), d[2].max(0.0)]);
+                outside + d[0].max(d[1]).max(d[2]).min(0.0)
+            }
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        let positive = |v: f64| v.is_finite() && v > 0.0;
+        match *self {
+            Primitive::Sphere { center, radius } => center.iter().all(|v| v.is_finite()) && positive(radius),
+            Primitive::Torus { center, major, minor } => {
+                center.iter().all(|v| v.is_finite()) && positive(major) && positive(minor)
+            }
+            Primitive::Box { center, half_size } => {
+                center.iter().all(|v| v.is_finite()) && half_size.iter().all(|&v| positive(v))
+            }
+        }
+    }
+}
+
+/// Union of signed-distance primitives with its camera and lighting
+///
+/// The camera looks down +z at the origin; +y is up. Rendering fails on a
+/// scene it can't draw, e.g. one with no primitives or a frame over the size
+/// limit, rather than guessing at what the caller meant.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SdfScene {
+    primitives: Vec<Primitive>,
+    width: usize,
+    height: usize,
+    ramp: Vec<char>,
+    light: Vec3,
+    specular: f64,
+    shininess: f64,
+    camera_distance: f64,
+}
+
+impl Default for SdfScene {
+    fn default() -> Self {
+        Self {
+            primitives: Vec::new(),
+            width: 80,
+            height: 40,
+            ramp: DEFAULT_RAMP.chars().collect(),
+            light: [-1.0, 1.0, -1.0],
+            specular: 0.5,
+            shininess: 16.0,
+            camera_distance: 4.0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SdfScene {
+    /// Empty 80x40 scene lit from the upper left, camera 4 units away
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SdfScene {
+        Self::default()
+    }
+
+    pub fn with_sphere(mut self, x: f64, y: f64, z: f64, radius: f64) -> SdfScene {
+        self.primitives.push(Primitive::Sphere { center: [x, y, z], radius });
+        self
+    }
+
+    /// Torus around the y axis: `major` is the ring radius, `minor` the
+    /// tube radius
+    pub fn with_torus(mut self, x: f64, y: f64, z: f64, major: f64, minor: f64) -> SdfScene {
+        self.primitives.push(Primitive::Torus { center: [x, y, z], major, minor });
+        self
+    }
+
+    /// Axis-aligned box with the given half extents
+    pub fn with_box(mut self, x: f64, y: f64, z: f64, half_x: f64, half_y: f64, half_z: f64) -> SdfScene {
+        self.primitives.push(Primitive::Box { center: [x, y, z], half_size: [half_x, half_y, half_z] });
+        self
+    }
+
+    pub fn with_size(mut self, width: usize, height: usize) -> SdfScene {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Shades from darkest to brightest; empty space is always blank
+    pub fn with_ramp(mut self, ramp: &str) -> SdfScene {
+        self.ramp = ramp.chars().collect();
+        self
+    }
+
+    /// Direction toward the light, in camera space
+    pub fn with_light(mut self, x: f64, y: f64, z: f64) -> SdfScene {
+        self.light = [x, y, z];
+        self
+    }
+
+    /// Phong highlight strength (0 disables it) and exponent
+    pub fn with_specular(mut self, strength: f64, shininess: f64) -> SdfScene {
+        self.specular = strength;
+        self.shininess = shininess;
+        self
+    }
+
+    pub fn with_camera_distance(mut self, distance: f64) -> SdfScene {
+        self.camera_distance = distance;
+        self
+    }
+}
+
+impl SdfScene {
+    fn validate(&self) -> Result<(), String> {
+        if self.primitives.is_empty() {
+            return Err("Scene has no primitives".to_string());
+        }
+        if let Some(index) = self.primitives.iter().position(|p| !p.is_valid()) {
+            return Err(format!("Primitive {} needs finite coordinates and positive sizes", index));
+        }
+        check_frame_size("Frame", "cells", self.width, self.height)?;
+        if self.ramp.is_empty() || self.ramp.contains(&'\n') {
+            return Err("Ramp must be non-empty and free of line breaks".to_string());
+        }
+        if !(self.light.iter().all(|v| v.is_finite()) && length(self.light) > 0.0) {
+            return Err("Light direction must be a finite, non-zero vector".to_string());
+        }
+        if !(self.specular.is_finite() && self.specular >= 0.0 && self.shininess.is_finite() && self.shininess > 0.0) {
+            return Err(format!(
+                "Specular strength must be non-negative and shininess positive, got {} and {}",
+                self.specular, self.shininess
+            ));
+        }
+        if !(self.camera_distance.is_finite() && self.camera_distance > 0.0) {
+            return Err(format!("Camera distance must be positive, got {}", self.camera_distance));
+        }
+        Ok(())
+    }
+
+    fn render(&self, angle_x: f64, angle_y: f64) -> String {
+        let (width, height) = (self.width, self.height);
+        let rotation = Rotation::new(angle_x, angle_y);
+        let light = normalize(self.light);
+        let camera = [0.0, 0.0, -self.camera_distance];
+        // Everything past the far side of a scene around the origin is empty
+        let far = self.camera_distance * 2.0 + 16.0;
+
+        // Nearest hit so far and its brightness, per cell
+        let mut depth = vec![f64::INFINITY; width * height];
+        let mut brightness = vec![0.0; width * height];
+
+        for primitive in &self.primitives {
+            // Turn the scene by marching through the inversely rotated ray
+            let distance = |p: Vec3| primitive.distance(rotation.apply_inverse(p));
+            for y in 0..height {
+                let v = 1.0 - 2.0 * (y as f64 + 0.5) / height as f64;
+                for x in 0..width {
+                    let u = (2.0 * (x as f64 + 0.5) / width as f64 - 1.0) * width as f64 / height as f64 * CELL_ASPECT;
+                    let direction = normalize([u, v, FOCAL_LENGTH]);
+
+                    let index = y * width + x;
+                    let Some(t) = march(&distance, camera, direction, far.min(depth[index])) else {
+                        continue;
+                    };
+                    depth[index] = t;
+
+                    let point = add(camera, scale(direction, t));
+                    let normal = surface_normal(&distance, point);
+                    let diffuse = dot(normal, light).max(0.0);
+                    // Phong highlight from the light reflected about the normal
+                    let reflected = sub(scale(normal, 2.0 * dot(normal, light)), light);
+                    let highlight = if diffuse > 0.0 {
+                        dot(reflected, scale(direction, -1.0)).max(0.0).powf(self.shininess)
+                    } else {
+                        0.0
+                    };
+                    brightness[index] = AMBIENT + (1.0 - AMBIENT) * diffuse + self.specular * highlight;
+                }
+            }
+        }
+
+        let last = self.ramp.len() - 1;
+        let mut result = String::with_capacity((width + 1) * height);
+        for (row_depth, row_brightness) in depth.chunks(width).zip(brightness.chunks(width)) {
+            for (&d, &b) in row_depth.iter().zip(row_brightness) {
+                if d.is_finite() {
+                    result.push(self.ramp[((b.clamp(0.0, 1.0) * last as f64).round() as usize).min(last)]);
+                } else {
+                    result.push(' ');
+                }
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// Rotation about x, then about y
+struct Rotation {
+    cos_x: f64,
+    sin_x: f64,
+    cos_y: f64,
+    sin_y: f64,
+}
+
+impl Rotation {
+    fn new(angle_x: f64, angle_y: f64) -> Self {
+        Rotation {
+            cos_x: angle_x.cos(),
+            sin_x: angle_x.sin(),
+            cos_y: angle_y.cos(),
+            sin_y: angle_y.sin(),
+        }
+    }
+
+    // Undo the rotation: about y first, then about x
+    fn apply_inverse(&self, p: Vec3) -> Vec3 {
+        let x = p[0] * self.cos_y - p[2] * self.sin_y;
+        let z = p[0] * self.sin_y + p[2] * self.cos_y;
+        let y = p[1] * self.cos_x + z * self.sin_x;
+        let z = -p[1] * self.sin_x + z * self.cos_x;
+        [x, y, z]
+    }
+}
+
+// Distance along the ray to the surface, if it is hit before `limit`
+fn march(distance: &impl Fn(Vec3) -> f64, origin: Vec3, direction: Vec3, limit: f64) -> Option<f64> {
+    let mut t = 0.0;
+    for _ in 0..MAX_STEPS {
+        let d = distance(add(origin, scale(direction, t)));
+        if d < HIT_DISTANCE {
+            return Some(t);
+        }
+        t += d;
+        if t >= limit {
+            return None;
+        }
+    }
+    None
+}
+
+// Gradient of the distance field by central differences
+fn surface_normal(distance: &impl Fn(Vec3) -> f64, p: Vec3) -> Vec3 {
+    const H: f64 = 1e-4;
+    let axis = |i: usize| {
+        let mut offset = [0.0; 3];
+        offset[i] = H;
+        distance(add(p, offset)) - distance(sub(p, offset))
+    };
+    normalize([axis(0), axis(1), axis(2)])
+}
+
+/// Render `scene` turned by `angle_x` radians about the x axis, then
+/// `angle_y` about the y axis
+///
+/// Each primitive is ray-marched on its own and a z-buffer keeps the
+/// nearest surface per cell, so overlapping primitives form a union.
+/// Surfaces get Lambert diffuse plus Phong specular shading.
+///
+/// # Returns
+/// One frame, `height` lines of `width` characters
+#[wasm_bindgen]
+pub fn generate_raymarch_frame(angle_x: f64, angle_y: f64, scene: &SdfScene) -> Result<String, String> {
+    scene.validate()?;
+    Ok(scene.render(angle_x, angle_y))
+}
+
+/// The classic spinning donut: an 80x40 torus turned by both angles
+#[wasm_bindgen]
+pub fn generate_donut_frame(angle_x: f64, angle_y: f64) -> String {
+    SdfScene::default().with_torus(0.0, 0.0, 0.0, 1.5, 0.6).render(angle_x, angle_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn shade_at(frame: &str, x: usize, y: usize) -> char {
+        frame.lines().nth(y).unwrap().chars().nth(x).unwrap()
+    }
+
+    #[test]
+    fn test_donut_has_a_hole_and_faces_the_light() {
+        // Turned to face the camera, the hole is in the middle of the frame
+        let frame = generate_donut_frame(FRAC_PI_2, 0.0);
+        assert_eq!(frame.lines().count(), 40);
+        assert!(frame.lines().all(|line| line.chars().count() == 80));
+        assert_eq!(shade_at(&frame, 40, 20), ' ');
+        assert_ne!(shade_at(&frame, 40, 9), ' ');
+        assert_ne!(shade_at(&frame, 40, 31), ' ');
+
+        // Lit from the upper left, so the upper left of a sphere is brighter
+        let sphere = SdfScene::new().with_size(40, 20).with_sphere(0.0, 0.0, 0.0, 1.0);
+        let frame = generate_raymarch_frame(0.0, 0.0, &sphere).unwrap();
+        let rank = |c: char| DEFAULT_RAMP.find(c).unwrap();
+        assert!(rank(shade_at(&frame, 16, 8)) > rank(shade_at(&frame, 24, 12)));
+        assert_eq!(shade_at(&frame, 0, 0), ' ');
+    }
+
+    #[test]
+    fn test_nearest_primitive_wins() {
+        let ball = SdfScene::new().with_size(30, 15).with_specular(0.0, 1.0).with_sphere(0.0, 0.0, -1.0, 0.6);
+        let wall = SdfScene::new().with_size(30, 15).with_specular(0.0, 1.0).with_box(0.0, 0.0, 1.0, 3.0, 3.0, 0.2);
+        let both = ball.clone().with_box(0.0, 0.0, 1.0, 3.0, 3.0, 0.2);
+        let ball = generate_raymarch_frame(0.0, 0.0, &ball).unwrap();
+        let wall = generate_raymarch_frame(0.0, 0.0, &wall).unwrap();
+        let both = generate_raymarch_frame(0.0, 0.0, &both).unwrap();
+
+        // The ball hides the wall where it covers it; elsewhere the wall shows
+        assert_eq!(shade_at(&both, 15, 7), shade_at(&ball, 15, 7));
+        assert_ne!(shade_at(&ball, 15, 7), shade_at(&wall, 15, 7));
+        assert_eq!(shade_at(&both, 2, 2), shade_at(&wall, 2, 2));
+
+        assert!(generate_raymarch_frame(0.0, 0.0, &SdfScene::new()).is_err());
+        assert!(generate_raymarch_frame(0.0, 0.0, &SdfScene::new().with_sphere(0.0, 0.0, 0.0, -1.0)).is_err());
+        let huge = SdfScene::new().with_sphere(0.0, 0.0, 0.0, 1.0).with_size(8192, 4096);
+        assert!(generate_raymarch_frame(0.0, 0.0, &huge).unwrap_err().contains("got 8192x4096"));
+    }
+}
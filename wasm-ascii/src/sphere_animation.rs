@@ -1,106 +1,316 @@
 use wasm_bindgen::prelude::*;
+use std::f64::consts::TAU;
 
-const PI: f64 = 3.14159265358979323846;
-const RADIUS: usize = 10;
-const WIDTH: usize = RADIUS * 4;
-const HEIGHT: usize = RADIUS * 2;
-const SHADE_CHARS: &[u8] = b".:-=+*#%@";
-const NUM_SHADES: usize = 9;
-const THICKNESS: f64 = 0.12; // Thickness in normalized coordinates (ensures continuous ring)
-
-/// Calculate the radius at a given angle with organic undulations
-/// The shape morphs as the animation angle changes, making it more dynamic
-fn get_radius_at_angle(shape_theta: f64, animation_angle: f64) -> f64 {
-    let base_radius = RADIUS as f64;
-    // Create more subtle organic undulations for a more circular shape
-    // The undulations phase-shift with animation angle, causing the shape to morph
-    let undulation1 = (shape_theta * 3.0 + animation_angle * 0.5).sin() * 0.15;
-    let undulation2 = (shape_theta * 5.0 + animation_angle * 0.8).sin() * 0.12;
-    let undulation3 = (shape_theta * 7.0 + animation_angle * 1.2).sin() * 0.08;
-    let undulation4 = (shape_theta * 11.0 + animation_angle * 0.3).sin() * 0.05;
-    
-    // Add a subtle overall morphing effect based on animation angle
-    let morph_factor = (animation_angle * 2.0).sin() * 0.1;
-    
-    base_radius + undulation1 + undulation2 + undulation3 + undulation4 + morph_factor
+use crate::frame_size::check_frame_size;
+
+/// Default shades from darkest to brightest lit
+pub(crate) const SHADE_CHARS: &[u8] = b".:-=+*#%@";
+
+// Most full turns `frames` may span to bring every phase back to the start
+const MAX_LOOP_TURNS: usize = 1000;
+
+// Most frames one `frames` call renders; 50 seconds at the hero's 20 fps
+const MAX_LOOP_FRAMES: usize = 1000;
+
+/// One undulation of the ring outline: `sin(frequency * theta + speed *
+/// angle) * amplitude`, in multiples of the radius
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Harmonic {
+    frequency: f64,
+    amplitude: f64,
+    speed: f64,
+}
+
+/// Rotating ring of shaded characters whose outline undulates over time
+///
+/// Frames are `4 * radius` characters wide and `2 * radius` lines tall.
+/// The defaults draw exactly what `generate_sphere_frame` does, so the
+/// landing-page animation can be varied one setter at a time.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SphereAnimation {
+    radius: usize,
+    thickness: f64,
+    shades: Vec<char>,
+    harmonics: Vec<Harmonic>,
+    // Light direction (x amplitude, y amplitude, z) and its turns per turn
+    // of the ring
+    light: [f64; 3],
+    light_speed: f64,
+}
+
+impl Default for SphereAnimation {
+    fn default() -> Self {
+        let harmonic = |frequency, amplitude, speed| Harmonic { frequency, amplitude, speed };
+        Self {
+            radius: 10,
+            // Thickness in normalized coordinates (ensures continuous ring)
+            thickness: 0.12,
+            shades: SHADE_CHARS.iter().map(|&b| b as char).collect(),
+            harmonics: vec![
+                harmonic(3.0, 0.015, 0.5),
+                harmonic(5.0, 0.012, 0.8),
+                harmonic(7.0, 0.008, 1.2),
+                harmonic(11.0, 0.005, 0.3),
+                // Overall morphing of the whole outline
+                harmonic(0.0, 0.01, 2.0),
+            ],
+            light: [1.0, 0.5, -0.7],
+            light_speed: 1.0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SphereAnimation {
+    /// The hero animation: radius 10, nine shades, five harmonics
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SphereAnimation {
+        Self::default()
+    }
+
+    /// Ring radius in lines; frames are 4x radius wide and 2x radius tall
+    pub fn with_radius(mut self, radius: usize) -> SphereAnimation {
+        self.radius = radius;
+        self
+    }
+
+    /// Ring thickness as a fraction of the radius
+    pub fn with_thickness(mut self, thickness: f64) -> SphereAnimation {
+        self.thickness = thickness;
+        self
+    }
+
+    /// Shade characters from darkest to brightest lit
+    pub fn with_shades(mut self, shades: &str) -> SphereAnimation {
+        self.shades = shades.chars().collect();
+        self
+    }
+
+    /// Outline undulations as flat `(frequency, amplitude, speed)` triples
+    ///
+    /// Frequency is waves around the ring, amplitude is a fraction of the
+    /// radius, and speed is phase turns per turn of the ring.
+    pub fn with_harmonics(mut self, harmonics: Vec<f64>) -> SphereAnimation {
+        self.harmonics = harmonics
+            .chunks(3)
+            .map(|h| Harmonic {
+                frequency: h[0],
+                amplitude: h.get(1).copied().unwrap_or(f64::NAN),
+                speed: h.get(2).copied().unwrap_or(f64::NAN),
+            })
+            .collect();
+        self
+    }
+
+    /// Light direction `(x * cos(a), y * sin(a), z)` where `a` turns `speed`
+    /// times per turn of the ring
+    pub fn with_light_path(mut self, x: f64, y: f64, z: f64, speed: f64) -> SphereAnimation {
+        self.light = [x, y, z];
+        self.light_speed = speed;
+        self
+    }
+
+    /// Frame with the ring turned by `angle` radians
+    pub fn frame(&self, angle: f64) -> Result<String, String> {
+        self.validate()?;
+        Ok(self.render(angle))
+    }
+
+    /// `frame_count` frames of one seamless loop
+    ///
+    /// A loop spans the fewest whole turns after which every harmonic and
+    /// the light are back where they started; 10 turns with the defaults.
+    /// At most 1000 frames are rendered per call.
+    pub fn frames(&self, frame_count: usize) -> Result<Vec<String>, String> {
+        self.validate()?;
+        if frame_count > MAX_LOOP_FRAMES {
+            return Err(format!("Frame count must be at most {}, got {}", MAX_LOOP_FRAMES, frame_count));
+        }
+        let period = self.loop_turns()? as f64 * TAU;
+        Ok((0..frame_count)
+            .map(|i| self.render(period * i as f64 / frame_count as f64))
+            .collect())
+    }
+
+    /// Number of full turns `frames` spans
+    pub fn loop_turns(&self) -> Result<usize, String> {
+        let speeds: Vec<f64> = self
+            .harmonics
+            .iter()
+            .map(|h| h.speed)
+            .chain([self.light_speed])
+            .collect();
+        (1..=MAX_LOOP_TURNS)
+            .find(|&turns| {
+                speeds
+                    .iter()
+                    .all(|speed| (speed * turns as f64 - (speed * turns as f64).round()).abs() < 1e-9)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Harmonic and light speeds don't repeat within {} turns; use simple fractions",
+                    MAX_LOOP_TURNS
+                )
+            })
+    }
+}
+
+impl SphereAnimation {
+    fn validate(&self) -> Result<(), String> {
+        if self.radius == 0 {
+            return Err("Radius must be at least 1".to_string());
+        }
+        check_frame_size("Ring frame", "cells", self.radius.saturating_mul(4), self.radius.saturating_mul(2))?;
+        if !(self.thickness.is_finite() && self.thickness > 0.0) {
+            return Err(format!("Thickness must be positive, got {}", self.thickness));
+        }
+        if self.shades.is_empty() {
+            return Err("Shade charset must not be empty".to_string());
+        }
+        let values = self.harmonics.iter().flat_map(|h| [h.frequency, h.amplitude, h.speed]);
+        if !values.chain(self.light).chain([self.light_speed]).all(f64::is_finite) {
+            return Err("Harmonics must be complete (frequency, amplitude, speed) triples and all values finite".to_string());
+        }
+        Ok(())
+    }
+
+    /// Calculate the radius at a given angle with organic undulations
+    /// The shape morphs as the animation angle changes, making it more dynamic
+    fn radius_at_angle(&self, shape_theta: f64, animation_angle: f64) -> f64 {
+        1.0 + self
+            .harmonics
+            .iter()
+            .map(|h| (shape_theta * h.frequency + animation_angle * h.speed).sin() * h.amplitude)
+            .sum::<f64>()
+    }
+
+    fn render(&self, angle: f64) -> String {
+        let radius = self.radius as f64;
+        let width = self.radius * 4;
+        let height = self.radius * 2;
+
+        // Calculate light direction (rotating light source, matching C code)
+        let light_angle = angle * self.light_speed;
+        let lx = self.light[0] * light_angle.cos();
+        let ly = self.light[1] * light_angle.sin();
+        let lz = self.light[2];
+        let l_norm = (lx * lx + ly * ly + lz * lz).sqrt();
+
+        let (lx_norm, ly_norm) = if l_norm != 0.0 {
+            (lx / l_norm, ly / l_norm)
+        } else {
+            (lx, ly)
+        };
+
+        let mut result = String::with_capacity((width + 1) * height);
+        // Check every pixel to ensure continuous coverage without gaps
+        for y in 0..height {
+            for x in 0..width {
+                // Convert screen coordinates to normalized coordinates [-1, 1]
+                let x_norm = (x as f64 / (radius * 2.0)) - 1.0;
+                let y_norm = (y as f64 / radius) - 1.0;
+
+                let distance = (x_norm * x_norm + y_norm * y_norm).sqrt();
+                let screen_theta = y_norm.atan2(x_norm);
+
+                // Rotate the shape by subtracting the animation angle
+                let shape_theta = screen_theta - angle;
+                let dist_from_ring = (distance - self.radius_at_angle(shape_theta, angle)).abs();
+
+                if dist_from_ring <= self.thickness {
+                    // The outward normal of the flat ring has no z component,
+                    // so only the light's x and y matter
+                    let dot = shape_theta.cos() * lx_norm + shape_theta.sin() * ly_norm;
+
+                    let shades = self.shades.len();
+                    let shade_index = ((dot.max(0.0) * shades as f64) as usize).min(shades - 1);
+                    result.push(self.shades[shade_index]);
+                } else {
+                    result.push(' ');
+                }
+            }
+            result.push('\n');
+        }
+
+        result
+    }
 }
 
 /// Generate a single frame of the animated organic circle
 /// Returns the ASCII art string for the current frame
 #[wasm_bindgen]
 pub fn generate_sphere_frame(angle: f64) -> String {
-    let mut grid = vec![vec![b' '; WIDTH]; HEIGHT];
-    
-    // Calculate light direction (rotating light source, matching C code)
-    let lx = angle.cos();
-    let ly = angle.sin() * 0.5;
-    let lz = -0.7;
-    let l_norm = (lx * lx + ly * ly + lz * lz).sqrt();
-    
-    let (lx_norm, ly_norm, lz_norm) = if l_norm != 0.0 {
-        (lx / l_norm, ly / l_norm, lz / l_norm)
-    } else {
-        (lx, ly, lz)
-    };
-    
-    // Iterate over all pixels to ensure continuous coverage without gaps
-    // This approach checks every pixel, preventing any breaks in the circle
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            // Convert screen coordinates to normalized coordinates [-1, 1]
-            // Matching C code: sx = RADIUS * (x_norm + 1.0) * 2, so x_norm = sx / (RADIUS * 2) - 1.0
-            let x_norm = (x as f64 / ((RADIUS as f64) * 2.0)) - 1.0;
-            let y_norm = (y as f64 / (RADIUS as f64)) - 1.0;
-            
-            // Calculate distance from center and angle
-            let distance = (x_norm * x_norm + y_norm * y_norm).sqrt();
-            let screen_theta = y_norm.atan2(x_norm);
-            
-            // Rotate the shape by subtracting the animation angle
-            // This makes the circle rotate around its center
-            let shape_theta = screen_theta - angle;
-            
-            // Get the expected radius at this angle (with organic undulations that morph)
-            let expected_radius = get_radius_at_angle(shape_theta, angle);
-            
-            // Convert expected radius to normalized coordinates
-            // The radius is in screen units (0 to ~RADIUS*2), normalize to match x_norm/y_norm scale
-            // Since x_norm goes from -1 to 1 (width 2), and radius is in [0, RADIUS*2] range
-            // We need to scale: expected_radius_norm = expected_radius / RADIUS
-            let expected_radius_norm = expected_radius / (RADIUS as f64);
-            
-            // Check if pixel is within the thick ring (distance matches expected radius ± thickness)
-            let dist_from_ring = (distance - expected_radius_norm).abs();
-            
-            if dist_from_ring <= THICKNESS {
-                // Calculate shading based on the shape's rotated angle relative to light source
-                // The normal of the circle at this point points outward
-                let normal_x = shape_theta.cos();
-                let normal_y = shape_theta.sin();
-                let normal_z = 0.0; // 2D circle
-                
-                // Dot product with light direction for shading
-                let dot = normal_x * lx_norm + normal_y * ly_norm + normal_z * lz_norm;
-                
-                // Clamp to [0, 1]
-                let dot_clamped = dot.max(0.0);
-                
-                // Map to shade character index
-                let shade_index = ((dot_clamped * NUM_SHADES as f64) as usize).min(NUM_SHADES - 1);
-                
-                grid[y][x] = SHADE_CHARS[shade_index];
-            }
-        }
+    SphereAnimation::default().render(angle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_frame_is_a_shaded_ring() {
+        let frame = generate_sphere_frame(0.0);
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(lines.len(), 20);
+        assert!(lines.iter().all(|line| line.len() == 40));
+        // Lit from the right at angle 0
+        let middle = lines[10].trim();
+        assert!(middle.starts_with('.'));
+        assert!(middle.ends_with('%'));
+
+        let small = SphereAnimation::new().with_radius(4).with_shades("ox").frame(1.0).unwrap();
+        assert_eq!(small.lines().count(), 8);
+        assert!(small.chars().all(|c| " ox\n".contains(c)));
+
+        assert!(SphereAnimation::new().with_radius(0).frame(0.0).is_err());
+        assert!(SphereAnimation::new().with_radius(2048).frame(0.0).is_err());
+        assert!(SphereAnimation::new().with_shades("").frame(0.0).is_err());
+        assert!(SphereAnimation::new().with_harmonics(vec![3.0, 0.1]).frame(0.0).is_err());
+    }
+
+    #[test]
+    fn test_default_frame_matches_original_animation() {
+        // Frame 17 of the landing page, which adds 0.1 to the angle per frame
+        let angle = (0..17).fold(0.0, |angle, _| angle + 0.1);
+        let expected = concat!(
+            "         +======----::::......          \n",
+            "      +++++======---::::..........      \n",
+            "     +++++++               .........    \n",
+            "   **++++                      .......  \n",
+            "  *****+                         .......\n",
+            " *****                             .....\n",
+            "*****                               ....\n",
+            "****                                 ...\n",
+            "***                                   ..\n",
+            "***                                   ..\n",
+            "+++                                   ..\n",
+            "+++                                   ..\n",
+            "++++                                  ..\n",
+            "+++=                                 ...\n",
+            "=====                               ....\n",
+            " ====-                             .....\n",
+            "  ------                         .......\n",
+            "   ----:::                    ........  \n",
+            "     :::::...              .........    \n",
+            "       ::.........  .............       \n",
+        );
+        assert_eq!(generate_sphere_frame(angle), expected);
     }
-    
-    // Convert grid to string
-    let mut result = String::new();
-    for row in grid.iter() {
-        let row_str = String::from_utf8_lossy(row);
-        result.push_str(&row_str);
-        result.push('\n');
+
+    #[test]
+    fn test_frames_form_a_loop() {
+        let animation = SphereAnimation::new();
+        assert_eq!(animation.loop_turns(), Ok(10));
+        let frames = animation.frames(6).unwrap();
+        assert_eq!(frames.len(), 6);
+        assert_eq!(frames[0], generate_sphere_frame(0.0));
+        assert_eq!(frames[3], generate_sphere_frame(10.0 * TAU / 2.0));
+        assert!(animation.frames(MAX_LOOP_FRAMES + 1).is_err());
+
+        let quarter = animation.clone().with_harmonics(vec![3.0, 0.1, 0.25]);
+        assert_eq!(quarter.loop_turns(), Ok(4));
+        let irrational = animation.with_light_path(1.0, 0.5, -0.7, 0.123456);
+        assert!(irrational.frames(4).is_err());
     }
-    
-    result
 }
-
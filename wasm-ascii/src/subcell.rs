@@ -0,0 +1,155 @@
+use crate::convert_options::RenderMode;
+
+// Quadrant blocks indexed by TL=1, TR=2, BL=4, BR=8
+pub(crate) const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+// Half blocks indexed by top=1, bottom=2
+const HALF_BLOCKS: [char; 4] = [' ', '▀', '▄', '█'];
+
+// Braille dot bits for (column, row) within the 2x4 cell
+pub(crate) const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Source pixels packed into one character by a sub-cell mode
+pub(crate) fn subcell_size(mode: RenderMode) -> (usize, usize) {
+    match mode {
+        RenderMode::Braille => (2, 4),
+        RenderMode::HalfBlock => (1, 2),
+        RenderMode::Quadrant => (2, 2),
+        RenderMode::Sextant => (2, 3),
+        RenderMode::Luminance | RenderMode::GlyphMatch => (1, 1),
+    }
+}
+
+// Sextant blocks indexed by TL=1, TR=2, ML=4, MR=8, BL=16, BR=32
+//
+// Unicode encodes the 60 sextants that are not empty, full or a plain
+// left/right half in bit order starting at U+1FB00.
+fn sextant_char(bits: u32) -> char {
+    match bits {
+        0 => ' ',
+        21 => '▌',
+        42 => '▐',
+        63 => '█',
+        _ => {
+            let skipped = (bits > 21) as u32 + (bits > 42) as u32;
+            char::from_u32(0x1FB00 + bits - 1 - skipped).unwrap_or('?')
+        }
+    }
+}
+
+// Character for a cell whose lit sub-pixels are set in `bits`
+//
+// Bits are numbered row-major across the cell, e.g. for quadrants
+// bit 0 is top-left and bit 3 bottom-right.
+fn subcell_char(mode: RenderMode, bits: u32) -> char {
+    match mode {
+        RenderMode::Braille => {
+            // Remap row-major bits to braille dot numbering
+            let mut dots = 0;
+            for i in 0..8 {
+                if bits & (1 << i) != 0 {
+                    dots |= BRAILLE_DOTS[i % 2][i / 2];
+                }
+            }
+            char::from_u32(0x2800 + dots).unwrap_or(' ')
+        }
+        RenderMode::HalfBlock => HALF_BLOCKS[bits as usize & 3],
+        RenderMode::Quadrant => QUADRANTS[bits as usize & 15],
+        RenderMode::Sextant => sextant_char(bits & 63),
+        RenderMode::Luminance | RenderMode::GlyphMatch => ' ',
+    }
+}
+
+/// Pack a binary image into sub-cell characters in `cells`
+///
+/// `lit` holds one flag per source pixel. Pixels past the right or bottom
+/// edge count as unlit. Returns the number of columns and rows.
+pub(crate) fn subcell_cells(
+    lit: &[bool],
+    width: usize,
+    height: usize,
+    mode: RenderMode,
+    cells: &mut Vec<char>,
+) -> (usize, usize) {
+    let (cell_w, cell_h) = subcell_size(mode);
+    let cols = width.div_ceil(cell_w);
+    let rows = height.div_ceil(cell_h);
+    cells.clear();
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let mut bits = 0;
+            for sy in 0..cell_h {
+                for sx in 0..cell_w {
+                    let (x, y) = (col * cell_w + sx, row * cell_h + sy);
+                    if x < width && y < height && lit[y * width + x] {
+                        bits |= 1 << (sy * cell_w + sx);
+                    }
+                }
+            }
+            cells.push(subcell_char(mode, bits));
+        }
+    }
+
+    (cols, rows)
+}
+
+/// Foreground (top pixel) and background (bottom pixel) colors for
+/// half blocks drawn as `▀`, into `top` and `bottom`
+///
+/// An odd final row repeats its top color as the background.
+pub(crate) fn half_block_colors(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    top: &mut Vec<[u8; 3]>,
+    bottom: &mut Vec<[u8; 3]>,
+) {
+    let rgb = |x: usize, y: usize| {
+        let idx = (y * width + x) * 4;
+        [data[idx], data[idx + 1], data[idx + 2]]
+    };
+    top.clear();
+    bottom.clear();
+
+    for row in 0..height.div_ceil(2) {
+        let bottom_y = (row * 2 + 1).min(height - 1);
+        top.extend((0..width).map(|x| rgb(x, row * 2)));
+        bottom.extend((0..width).map(|x| rgb(x, bottom_y)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sextant_mapping_skips_half_blocks() {
+        assert_eq!(sextant_char(1), '\u{1FB00}');
+        assert_eq!(sextant_char(20), '\u{1FB13}');
+        assert_eq!(sextant_char(22), '\u{1FB14}');
+        assert_eq!(sextant_char(62), '\u{1FB3B}');
+    }
+
+    #[test]
+    fn test_braille_packs_2x4_dots() {
+        // Left column fully lit, right column only the bottom dot
+        let lit = [true, false, true, false, true, false, true, true];
+        let mut cells = Vec::new();
+        let (cols, rows) = subcell_cells(&lit, 2, 4, RenderMode::Braille, &mut cells);
+        assert_eq!((cols, rows), (1, 1));
+        assert_eq!(cells, vec!['\u{28C7}']);
+    }
+
+    #[test]
+    fn test_quadrants_and_partial_cells() {
+        // 3x2 image: the last column is half a cell wide
+        let lit = [true, false, true, false, true, true];
+        let mut cells = Vec::new();
+        let (cols, rows) = subcell_cells(&lit, 3, 2, RenderMode::Quadrant, &mut cells);
+        assert_eq!((cols, rows), (2, 1));
+        assert_eq!(cells, vec!['▚', '▌']);
+    }
+}
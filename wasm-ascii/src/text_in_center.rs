@@ -1,178 +1,330 @@
 use wasm_bindgen::prelude::*;
 
-// Simple 5x7 bitmap font for ASCII art
-// Each character is 5 wide x 7 tall
-const CHAR_HEIGHT: usize = 7;
-
-// Font data structure - stores character patterns
-type CharPattern = [&'static str; CHAR_HEIGHT];
-
-// Font data stored as a const array for better maintainability
-// Format: (character, pattern)
-const FONT_DATA: &[(&str, CharPattern)] = &[
-    ("O", [
-        " ### ",
-        "#   #",
-        "#   #",
-        "#   #",
-        "#   #",
-        "#   #",
-        " ### ",
-    ]),
-    (".", [
-        "     ",
-        "     ",
-        "     ",
-        "     ",
-        "     ",
-        "  #  ",
-        "  #  ",
-    ]),
-    ("A", [
-        "  #  ",
-        " # # ",
-        "#   #",
-        "#####",
-        "#   #",
-        "#   #",
-        "#   #",
-    ]),
-    ("S", [
-        " ### ",
-        "#   #",
-        "#    ",
-        " ### ",
-        "    #",
-        "#   #",
-        " ### ",
-    ]),
-    ("C", [
-        " ### ",
-        "#   #",
-        "#    ",
-        "#    ",
-        "#    ",
-        "#   #",
-        " ### ",
-    ]),
-    ("I", [
-        "#####",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-        "#####",
-    ]),
-    (" ", [
-        "     ",
-        "     ",
-        "     ",
-        "     ",
-        "     ",
-        "     ",
-        "     ",
-    ]),
-    ("R", [
-        "#### ",
-        "#   #",
-        "#   #",
-        "#### ",
-        "#  # ",
-        "#   #",
-        "#   #",
-    ]),
-    ("T", [
-        "#####",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-        "  #  ",
-    ]),
-    ("G", [
-        " ### ",
-        "#   #",
-        "#    ",
-        "#  ##",
-        "#   #",
-        "#   #",
-        " ### ",
-    ]),
-    ("E", [
-        "#####",
-        "#    ",
-        "#    ",
-        "#### ",
-        "#    ",
-        "#    ",
-        "#####",
-    ]),
-    ("N", [
-        "#   #",
-        "##  #",
-        "# # #",
-        "#  ##",
-        "#   #",
-        "#   #",
-        "#   #",
-    ]),
-];
-
-// Empty pattern for unknown characters
-const EMPTY_PATTERN: CharPattern = [
-    "     ",
-    "     ",
-    "     ",
-    "     ",
-    "     ",
-    "     ",
-    "     ",
-];
-
-// Get character pattern from font data
-// Uses linear search - efficient for small font sets
-fn get_char_pattern(c: char) -> CharPattern {
-    let c_upper = c.to_ascii_uppercase();
-    
-    // Search through font data
-    for (chars, pattern) in FONT_DATA {
-        if chars.contains(c_upper) {
-            return *pattern;
+use crate::font::{glyph_pixel, glyph_rows, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::frame_size::{check_frame_size, clamp_frame_size};
+use crate::sphere_animation::SHADE_CHARS;
+
+// Characters outside printable ASCII render as this glyph
+const FALLBACK_CHAR: char = '?';
+
+/// Horizontal placement of each text line
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Vertical placement of the block of text lines
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// Which glyph pixels are drawn
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerStyle {
+    /// Glyph strokes
+    Filled,
+    /// The blank pixels bordering each stroke, leaving strokes hollow
+    Outline,
+    /// Strokes with a shadow one pixel down and right in the lightest
+    /// gradient character
+    Shadow,
+}
+
+/// Character choice for drawn pixels
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerFill {
+    /// The fill character everywhere
+    Solid,
+    /// Gradient charset from the top of the text to the bottom
+    RowGradient,
+    /// Gradient charset from the left of the text to the right
+    ColumnGradient,
+    /// Gradient charset from the top-left corner to the bottom-right
+    DiagonalGradient,
+}
+
+/// Options for `generate_text_layout`
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct TextLayoutOptions {
+    align: TextAlign,
+    vertical_align: VerticalAlign,
+    letter_spacing: usize,
+    line_spacing: usize,
+    style: BannerStyle,
+    fill: BannerFill,
+    fill_char: char,
+    gradient: Vec<char>,
+}
+
+impl Default for TextLayoutOptions {
+    fn default() -> Self {
+        Self {
+            align: TextAlign::Center,
+            vertical_align: VerticalAlign::Middle,
+            letter_spacing: 1,
+            line_spacing: 1,
+            style: BannerStyle::Filled,
+            fill: BannerFill::Solid,
+            fill_char: '#',
+            gradient: SHADE_CHARS.iter().map(|&b| b as char).collect(),
         }
     }
-    
-    EMPTY_PATTERN
 }
 
-/// Render text as ASCII art
-fn render_ascii_text(text: &str) -> Vec<String> {
-    let mut result = vec![String::new(); CHAR_HEIGHT];
-    
-    for c in text.chars() {
-        let pattern = get_char_pattern(c);
-        for (i, line) in pattern.iter().enumerate() {
-            result[i].push_str(line);
-            // Add spacing between characters
-            result[i].push(' ');
+#[wasm_bindgen]
+impl TextLayoutOptions {
+    /// Centered both ways, one blank column between letters and one blank
+    /// row between lines, filled strokes drawn with `#`
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TextLayoutOptions {
+        Self::default()
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> TextLayoutOptions {
+        self.align = align;
+        self
+    }
+
+    pub fn with_vertical_align(mut self, vertical_align: VerticalAlign) -> TextLayoutOptions {
+        self.vertical_align = vertical_align;
+        self
+    }
+
+    /// Blank columns between adjacent letters
+    pub fn with_letter_spacing(mut self, letter_spacing: usize) -> TextLayoutOptions {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Blank rows between lines of text
+    pub fn with_line_spacing(mut self, line_spacing: usize) -> TextLayoutOptions {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_style(mut self, style: BannerStyle) -> TextLayoutOptions {
+        self.style = style;
+        self
+    }
+
+    pub fn with_fill(mut self, fill: BannerFill) -> TextLayoutOptions {
+        self.fill = fill;
+        self
+    }
+
+    /// Character for `BannerFill::Solid`
+    pub fn with_fill_char(mut self, fill_char: char) -> TextLayoutOptions {
+        self.fill_char = fill_char;
+        self
+    }
+
+    /// Gradient characters from lightest to darkest, `.:-=+*#%@` by
+    /// default; an empty charset keeps the current one
+    pub fn with_gradient_charset(mut self, charset: &str) -> TextLayoutOptions {
+        if !charset.is_empty() {
+            self.gradient = charset.chars().collect();
         }
+        self
     }
-    
-    result
+}
+
+impl TextLayoutOptions {
+    // Character for a drawn pixel at (x, y) of a text block `width` x
+    // `height` pixels, relative to its top-left corner
+    fn fill_char_at(&self, x: usize, y: usize, width: usize, height: usize) -> char {
+        let (position, span) = match self.fill {
+            BannerFill::Solid => return self.fill_char,
+            BannerFill::RowGradient => (y, height),
+            BannerFill::ColumnGradient => (x, width),
+            BannerFill::DiagonalGradient => (x + y, width + height - 1),
+        };
+        let last = self.gradient.len() - 1;
+        let index = if span > 1 {
+            (position.min(span - 1) * last + (span - 1) / 2) / (span - 1)
+        } else {
+            0
+        };
+        self.gradient[index]
+    }
+}
+
+impl TextAlign {
+    // Columns left of a line `used` wide in a grid `total` wide
+    fn offset(self, used: usize, total: usize) -> usize {
+        let free = total.saturating_sub(used);
+        match self {
+            Self::Left => 0,
+            Self::Center => free / 2,
+            Self::Right => free,
+        }
+    }
+}
+
+impl VerticalAlign {
+    // Rows above a block `used` tall in a grid `total` tall
+    fn offset(self, used: usize, total: usize) -> usize {
+        let free = total.saturating_sub(used);
+        match self {
+            Self::Top => 0,
+            Self::Middle => free / 2,
+            Self::Bottom => free,
+        }
+    }
+}
+
+// Split `text` into lines of at most `max_chars` characters, breaking at
+// spaces where possible and inside words longer than a whole line
+fn wrap_text(text: &str, max_chars: usize) -> Vec<Vec<char>> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line: Vec<char> = Vec::new();
+        for word in paragraph.split_whitespace() {
+            let word: Vec<char> = word.chars().collect();
+            if !line.is_empty() && line.len() + 1 + word.len() <= max_chars {
+                line.push(' ');
+                line.extend_from_slice(&word);
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            let mut chunks = word.chunks(max_chars).peekable();
+            while let Some(chunk) = chunks.next() {
+                if chunks.peek().is_some() {
+                    lines.push(chunk.to_vec());
+                } else {
+                    line = chunk.to_vec();
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Lay out text with the embedded 5x7 font in a `width` x `height` grid
+///
+/// `\n` starts a new line and long lines wrap at spaces to fit `width`.
+/// Text taller than the grid starts at the top and is cut off at the
+/// bottom edge, so a grid shorter than one glyph still shows its top rows;
+/// a grid narrower than one glyph stays blank. Outlines and shadows reach
+/// one pixel past the glyphs and are clipped at the grid edge. Characters
+/// outside printable ASCII render as `?`.
+///
+/// # Arguments
+/// * `text` - Text to render
+/// * `width` - Grid width in characters
+/// * `height` - Grid height in lines
+/// * `options` - Alignment, spacing, style and fill
+///
+/// # Returns
+/// `height` lines of `width` characters, or an error if the grid is empty
+/// or over the frame size limit
+#[wasm_bindgen]
+pub fn generate_text_layout(
+    text: &str,
+    width: usize,
+    height: usize,
+    options: &TextLayoutOptions,
+) -> Result<String, String> {
+    check_frame_size("Text grid", "cells", width, height)?;
+
+    // Glyph pixels, and the bounding box of the laid out text
+    let mut lit = vec![false; width * height];
+    let (mut left_edge, mut right_edge) = (width, 0);
+    let (mut top, mut block_height) = (0, 0);
+
+    let advance = GLYPH_WIDTH.saturating_add(options.letter_spacing);
+    let line_advance = GLYPH_HEIGHT.saturating_add(options.line_spacing);
+    let max_chars = width.saturating_add(options.letter_spacing) / advance;
+    // Lines that start inside the grid; the last may be cut off
+    let max_lines = height.div_ceil(line_advance);
+
+    if max_chars > 0 {
+        let mut lines = wrap_text(text, max_chars);
+        lines.truncate(max_lines);
+
+        block_height = lines.len() * GLYPH_HEIGHT + lines.len().saturating_sub(1) * options.line_spacing;
+        top = options.vertical_align.offset(block_height, height);
+        let fallback = glyph_rows(FALLBACK_CHAR).expect("fallback glyph is printable ASCII");
+
+        for (row, line) in lines.iter().enumerate() {
+            let line_width = line.len() * GLYPH_WIDTH + line.len().saturating_sub(1) * options.letter_spacing;
+            let left = options.align.offset(line_width, width);
+            if !line.is_empty() {
+                left_edge = left_edge.min(left);
+                right_edge = right_edge.max(left + line_width);
+            }
+            let y0 = top + row * line_advance;
+            for (col, &c) in line.iter().enumerate() {
+                let rows = glyph_rows(c).unwrap_or(fallback);
+                let x0 = left + col * advance;
+                for y in 0..GLYPH_HEIGHT.min(height - y0) {
+                    for x in 0..GLYPH_WIDTH {
+                        if glyph_pixel(rows, x, y) {
+                            lit[(y0 + y) * width + x0 + x] = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let block_width = right_edge.saturating_sub(left_edge);
+    let is_lit = |x: usize, y: usize| x < width && y < height && lit[y * width + x];
+
+    let mut result = String::with_capacity((width + 1) * height);
+    for y in 0..height {
+        for x in 0..width {
+            let drawn = match options.style {
+                BannerStyle::Filled | BannerStyle::Shadow => is_lit(x, y),
+                BannerStyle::Outline => {
+                    !is_lit(x, y)
+                        && (y.saturating_sub(1)..=y + 1)
+                            .any(|ny| (x.saturating_sub(1)..=x + 1).any(|nx| is_lit(nx, ny)))
+                }
+            };
+            let c = if drawn {
+                options.fill_char_at(x.saturating_sub(left_edge), y.saturating_sub(top), block_width, block_height)
+            } else if options.style == BannerStyle::Shadow && x > 0 && y > 0 && is_lit(x - 1, y - 1) {
+                options.gradient[0]
+            } else {
+                ' '
+            };
+            result.push(c);
+        }
+        result.push('\n');
+    }
+    Ok(result)
 }
 
 /// Generate ASCII art text centered in a grid
+///
+/// Shorthand for `generate_text_layout` with default options. Callers get
+/// the grid back directly, so a size the layout would reject is clamped to
+/// the nearest one it accepts instead.
 #[wasm_bindgen]
 pub fn generate_text_in_center(text: &str, width: usize, height: usize) -> String {
-    // Render the text as ASCII art
-    let ascii_lines = render_ascii_text(text);
-    let ascii_width = if !ascii_lines.is_empty() {
-        ascii_lines[0].len()
-    } else {
-        0
-    };
-    let ascii_height = ascii_lines.len();
+    let (width, height) = clamp_frame_size(width, height);
+    generate_text_layout(text, width, height, &TextLayoutOptions::default()).expect("clamped grid size is in range")
+}
+
+/// Center `lines` in a `width` x `height` grid of characters
+///
+/// Lines wider than the grid are cut off on the right.
+pub(crate) fn center_in_grid(lines: &[String], width: usize, height: usize) -> String {
+    let ascii_width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let ascii_height = lines.len();
     
     // Calculate centering
     let horizontal_padding = if ascii_width < width {
@@ -198,22 +350,20 @@ pub fn generate_text_in_center(text: &str, width: usize, height: usize) -> Strin
     }
     
     // ASCII art lines
-    for line in &ascii_lines {
+    for line in lines {
         // Left padding
         for _ in 0..horizontal_padding {
             result.push(' ');
         }
         
         // ASCII line (truncate if needed)
-        let line_to_show = if line.len() > width - horizontal_padding {
-            &line[..width - horizontal_padding]
-        } else {
-            line.as_str()
-        };
-        result.push_str(line_to_show);
+        let mut used = horizontal_padding;
+        for c in line.chars().take(width - horizontal_padding) {
+            result.push(c);
+            used += 1;
+        }
         
         // Right padding
-        let used = horizontal_padding + line_to_show.len();
         for _ in used..width {
             result.push(' ');
         }
@@ -236,12 +386,117 @@ pub fn generate_text_in_center(text: &str, width: usize, height: usize) -> Strin
 mod tests {
     use super::*;
 
+    fn layout(text: &str, width: usize, height: usize, options: &TextLayoutOptions) -> Vec<String> {
+        generate_text_layout(text, width, height, options).unwrap().lines().map(String::from).collect()
+    }
+
+    // Draw `text` in a grid exactly as big as its glyphs
+    fn glyphs(text: &str) -> Vec<String> {
+        let width = text.chars().count() * (GLYPH_WIDTH + 1) - 1;
+        layout(text, width, GLYPH_HEIGHT, &TextLayoutOptions::new())
+    }
+
+    #[test]
+    fn test_renders_lowercase_digits_and_punctuation() {
+        for c in "az09!?@~".chars() {
+            let lines = glyphs(&c.to_string());
+            assert!(lines.iter().any(|line| line.contains('#')), "{:?} rendered blank", c);
+        }
+        // Lowercase has its own glyphs instead of being uppercased
+        assert_ne!(glyphs("a"), glyphs("A"));
+        assert_eq!(glyphs("\u{e9}"), glyphs("?"));
+        assert_eq!(glyphs("O")[0], " ### ");
+    }
+
     #[test]
-    fn test_generate_text_in_center() {
-        let text = "Hello, world!";
-        let width = 10;
-        let height = 10;
-        let ascii = generate_text_in_center(text, width, height);
-        assert_eq!(ascii, "Hello, world!\nHello, world!\nHello, world!\nHello, world!\nHello, world!\nHello, world!\nHello, world!\nHello, world!\nHello, world!\nHello, world!\n");
-    }
-}
\ No newline at end of file
+    fn test_wraps_words_and_honors_newlines() {
+        assert_eq!(wrap_text("one two three", 7), vec!["one two".chars().collect::<Vec<_>>(), "three".chars().collect()]);
+        assert_eq!(wrap_text("abcdefgh", 3).len(), 3);
+        assert_eq!(wrap_text("a\n\nb", 10).len(), 3);
+
+        // Two letters per line in 11 columns; three lines of 7 rows with one
+        // spacing row exactly fill 23 rows
+        let options = TextLayoutOptions::new();
+        let lines = layout("ab cd\nef", 11, 23, &options);
+        assert_eq!(lines.len(), 23);
+        assert!(lines.iter().all(|line| line.chars().count() == 11));
+        assert_eq!(lines[7], " ".repeat(11));
+        assert_eq!(lines[8..15], glyphs("cd")[..]);
+        assert_eq!(lines[16..23], glyphs("ef")[..]);
+
+        // Text taller than the grid is cut at the bottom edge
+        let lines = layout("ab cd", 11, 10, &options);
+        assert_eq!(lines[..7], glyphs("ab")[..]);
+        assert_eq!(lines[8..], glyphs("cd")[..2]);
+    }
+
+    #[test]
+    fn test_hero_banner_draws_its_text() {
+        // The landing page's call: two lines that fill the grid
+        let lines: Vec<String> = generate_text_in_center("O.ASCII Art generator", 80, 15).lines().map(String::from).collect();
+        let line = |text: &str| generate_text_in_center(text, 80, 7).lines().map(String::from).collect::<Vec<_>>();
+        assert_eq!(lines[..7], line("O.ASCII Art")[..]);
+        assert_eq!(lines[8..], line("generator")[..]);
+        assert!(lines[0].contains('#'));
+
+        // The grid it used to ask for is shorter than a glyph but not blank
+        assert!(generate_text_in_center("O.ASCII Art generator", 40, 5).contains('#'));
+    }
+
+    #[test]
+    fn test_alignment_and_spacing() {
+        let top_left = TextLayoutOptions::new()
+            .with_align(TextAlign::Left)
+            .with_vertical_align(VerticalAlign::Top)
+            .with_letter_spacing(2);
+        let lines = layout("II", 20, 9, &top_left);
+        assert!(lines[0].starts_with("#####  #####        "));
+        assert_eq!(lines[8], " ".repeat(20));
+
+        let bottom_right = top_left.with_align(TextAlign::Right).with_vertical_align(VerticalAlign::Bottom);
+        let lines = layout("II", 20, 9, &bottom_right);
+        assert_eq!(lines[2], "        #####  #####");
+        assert_eq!(lines[0], " ".repeat(20));
+
+        // Grids narrower than a glyph stay blank instead of panicking
+        assert_eq!(generate_text_in_center("Hello", 3, 2), "   \n   \n");
+
+        // Out-of-range grids and spacing are errors or clamped, never a panic
+        let options = TextLayoutOptions::new();
+        assert!(generate_text_layout("Hello", 0, 0, &options).is_err());
+        assert!(generate_text_layout("Hello", 1 << 20, 1 << 20, &options).is_err());
+        assert_eq!(generate_text_in_center("Hello", 0, 0), " \n");
+        let wide = options.with_letter_spacing(usize::MAX).with_line_spacing(usize::MAX);
+        assert_eq!(generate_text_layout("Hi", 5, 7, &wide).unwrap(), layout("H", 5, 7, &TextLayoutOptions::new()).join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_fill_characters_and_styles() {
+        let options = TextLayoutOptions::new().with_fill_char('@');
+        assert_eq!(layout("I", 5, 7, &options)[0], "@@@@@");
+
+        // The 'I' bar spans the full gradient from top to bottom
+        let rows = TextLayoutOptions::new().with_fill(BannerFill::RowGradient);
+        let lines = layout("I", 5, 7, &rows);
+        assert_eq!(lines[0], ".....");
+        assert_eq!(lines[6], "@@@@@");
+        let columns = TextLayoutOptions::new().with_fill(BannerFill::ColumnGradient).with_gradient_charset("ab");
+        assert_eq!(layout("I", 5, 7, &columns)[0], "aabbb");
+        let diagonal = TextLayoutOptions::new().with_fill(BannerFill::DiagonalGradient).with_gradient_charset("");
+        assert_eq!(layout("I", 5, 7, &diagonal)[6].chars().last(), Some('@'));
+
+        // Outlines surround the stroke and leave it hollow
+        let outline = TextLayoutOptions::new().with_style(BannerStyle::Outline);
+        let lines = layout("I", 7, 9, &outline);
+        assert_eq!(lines[0], "#######");
+        assert_eq!(lines[1], "#     #");
+        assert_eq!(lines[2], "### ###");
+        assert_eq!(lines[3], "  # #  ");
+
+        let shadow = TextLayoutOptions::new().with_style(BannerStyle::Shadow);
+        let lines = layout("I", 7, 9, &shadow);
+        assert_eq!(lines[1], " ##### ");
+        assert_eq!(lines[2], "  .#...");
+        assert_eq!(lines[8], "  .....");
+    }
+}
@@ -0,0 +1,213 @@
+use wasm_bindgen::prelude::*;
+
+use crate::clamp;
+use crate::convert_options::ConvertOptions;
+
+/// Histogram equalization applied by the tone stage
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equalization {
+    /// Keep the histogram as is
+    None,
+    /// One equalization curve for the whole image
+    Global,
+    /// Contrast-limited adaptive equalization over tiles
+    Clahe,
+}
+
+/// Tone stage run on the adjusted grayscale before character mapping
+///
+/// Applies, in order, auto-levels stretching, histogram equalization and
+/// gamma correction. Each step is skipped at its neutral setting.
+pub(crate) fn apply_tone(gray: &mut [f64], width: usize, height: usize, options: &ConvertOptions) {
+    if options.auto_levels > 0.0 {
+        auto_levels(gray, options.auto_levels);
+    }
+    match options.equalization {
+        Equalization::None => {}
+        Equalization::Global => equalize(gray),
+        Equalization::Clahe => clahe(gray, width, height, options.clahe_tile_size, options.clahe_clip_limit),
+    }
+    if options.gamma != 1.0 {
+        let exponent = 1.0 / options.gamma;
+        for v in gray.iter_mut() {
+            *v = 255.0 * (*v / 255.0).powf(exponent);
+        }
+    }
+}
+
+fn histogram<'a>(values: impl Iterator<Item = &'a f64>) -> [f64; 256] {
+    let mut hist = [0.0; 256];
+    for &v in values {
+        hist[clamp(v.round(), 0.0, 255.0) as usize] += 1.0;
+    }
+    hist
+}
+
+// Cumulative histogram scaled to 0-255, a lookup table for equalization
+fn cdf_lut(hist: &[f64; 256]) -> [f64; 256] {
+    let total: f64 = hist.iter().sum();
+    let mut lut = [0.0; 256];
+    let mut sum = 0.0;
+    for (entry, &count) in lut.iter_mut().zip(hist) {
+        sum += count;
+        *entry = sum / total * 255.0;
+    }
+    lut
+}
+
+// Look up a fractional gray level, interpolating between table entries
+fn lookup(lut: &[f64; 256], v: f64) -> f64 {
+    let v = clamp(v, 0.0, 255.0);
+    let i = v.floor() as usize;
+    let j = (i + 1).min(255);
+    lut[i] + (lut[j] - lut[i]) * (v - i as f64)
+}
+
+// Stretch the range between the `percentile` darkest and brightest pixels
+// to 0-255, clipping the pixels beyond it
+fn auto_levels(gray: &mut [f64], percentile: f64) {
+    let hist = histogram(gray.iter());
+    let cutoff = gray.len() as f64 * percentile / 100.0;
+
+    let mut low = 0;
+    let mut sum = 0.0;
+    for (level, &count) in hist.iter().enumerate() {
+        sum += count;
+        if sum > cutoff {
+            low = level;
+            break;
+        }
+    }
+    let mut high = 255;
+    sum = 0.0;
+    for (level, &count) in hist.iter().enumerate().rev() {
+        sum += count;
+        if sum > cutoff {
+            high = level;
+            break;
+        }
+    }
+
+    if high <= low {
+        return;
+    }
+    let scale = 255.0 / (high - low) as f64;
+    for v in gray.iter_mut() {
+        *v = clamp((*v - low as f64) * scale, 0.0, 255.0);
+    }
+}
+
+fn equalize(gray: &mut [f64]) {
+    let lut = cdf_lut(&histogram(gray.iter()));
+    for v in gray.iter_mut() {
+        *v = lookup(&lut, *v);
+    }
+}
+
+// CLAHE: equalize each `tile_size` square separately, with histogram bins
+// clipped at `clip_limit` times the average bin height so noise in flat
+// areas is not amplified, then blend the four nearest tile curves
+// bilinearly to hide tile seams
+fn clahe(gray: &mut [f64], width: usize, height: usize, tile_size: usize, clip_limit: f64) {
+    let tiles_x = width.div_ceil(tile_size);
+    let tiles_y = height.div_ceil(tile_size);
+
+    let mut luts = Vec::with_capacity(tiles_x * tiles_y);
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let (x0, y0) = (tx * tile_size, ty * tile_size);
+            let (x1, y1) = ((x0 + tile_size).min(width), (y0 + tile_size).min(height));
+            let mut hist = histogram((y0..y1).flat_map(|y| &gray[y * width + x0..y * width + x1]));
+
+            let limit = (clip_limit * ((x1 - x0) * (y1 - y0)) as f64 / 256.0).max(1.0);
+            let mut excess = 0.0;
+            for count in hist.iter_mut() {
+                if *count > limit {
+                    excess += *count - limit;
+                    *count = limit;
+                }
+            }
+            for count in hist.iter_mut() {
+                *count += excess / 256.0;
+            }
+
+            luts.push(cdf_lut(&hist));
+        }
+    }
+
+    // Fractional tile coordinate of a pixel, measured between tile centers
+    let neighbors = |p: usize, tiles: usize| {
+        let f = ((p as f64 + 0.5) / tile_size as f64 - 0.5).max(0.0);
+        let t0 = (f as usize).min(tiles - 1);
+        let t1 = (t0 + 1).min(tiles - 1);
+        (t0, t1, (f - t0 as f64).min(1.0))
+    };
+
+    for y in 0..height {
+        let (ty0, ty1, wy) = neighbors(y, tiles_y);
+        for x in 0..width {
+            let (tx0, tx1, wx) = neighbors(x, tiles_x);
+            let v = gray[y * width + x];
+            let top = lookup(&luts[ty0 * tiles_x + tx0], v) * (1.0 - wx) + lookup(&luts[ty0 * tiles_x + tx1], v) * wx;
+            let bottom = lookup(&luts[ty1 * tiles_x + tx0], v) * (1.0 - wx) + lookup(&luts[ty1 * tiles_x + tx1], v) * wx;
+            gray[y * width + x] = top * (1.0 - wy) + bottom * wy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distinct_levels(gray: &[f64]) -> usize {
+        histogram(gray.iter()).iter().filter(|&&count| count > 0.0).count()
+    }
+
+    #[test]
+    fn test_auto_levels_and_equalization_spread_dark_images() {
+        // An underexposed ramp using only levels 10-40
+        let dark: Vec<f64> = (0..256).map(|i| 10.0 + (i % 31) as f64).collect();
+
+        let mut stretched = dark.clone();
+        auto_levels(&mut stretched, 1.0);
+        assert_eq!(stretched.iter().cloned().fold(f64::MAX, f64::min), 0.0);
+        assert_eq!(stretched.iter().cloned().fold(0.0, f64::max), 255.0);
+
+        let mut equalized = dark.clone();
+        equalize(&mut equalized);
+        assert_eq!(distinct_levels(&equalized), distinct_levels(&dark));
+        assert!(equalized.iter().cloned().fold(0.0, f64::max) > 250.0);
+    }
+
+    #[test]
+    fn test_clahe_boosts_local_contrast_in_each_tile() {
+        // Left half dim, right half bright, each with faint texture
+        let (width, height) = (32, 16);
+        let gray: Vec<f64> = (0..width * height)
+            .map(|i| {
+                let base = if i % width < 16 { 20.0 } else { 200.0 };
+                base + ((i / width + i) % 4) as f64
+            })
+            .collect();
+        let mut out = gray.clone();
+        clahe(&mut out, width, height, 16, 4.0);
+
+        let range = |values: &[f64], x0: usize| {
+            let tile: Vec<f64> = (0..height).flat_map(|y| values[y * width + x0..y * width + x0 + 8].to_vec()).collect();
+            tile.iter().cloned().fold(0.0, f64::max) - tile.iter().cloned().fold(f64::MAX, f64::min)
+        };
+        assert!(range(&out, 0) > 4.0 * range(&gray, 0));
+        assert!(range(&out, 24) > 4.0 * range(&gray, 24));
+    }
+
+    #[test]
+    fn test_gamma_brightens_midtones() {
+        let options = ConvertOptions::new().with_gamma(2.2);
+        let mut gray = vec![0.0, 64.0, 255.0];
+        apply_tone(&mut gray, 3, 1, &options);
+        assert_eq!(gray[0], 0.0);
+        assert!(gray[1] > 64.0 * 1.5);
+        assert_eq!(gray[2], 255.0);
+    }
+}